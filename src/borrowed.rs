@@ -0,0 +1,126 @@
+use std::borrow::Cow;
+
+use crate::{Map, Number, Tree, Value};
+
+/// A JSON value whose strings and keys borrow from the parsed text where possible.
+///
+/// Only strings with escapes need their own allocation, which makes
+/// [`from_str_borrowed`](crate::from_str_borrowed) a lot cheaper than
+/// [`from_str`](crate::from_str) for documents that are mostly strings.
+#[derive(PartialEq, Debug, Clone)]
+pub enum BorrowedValue<'a> {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Object(Map<Cow<'a, str>, BorrowedValue<'a>>),
+    Array(Vec<BorrowedValue<'a>>),
+}
+
+impl BorrowedValue<'_> {
+    /// Copies the borrowed strings, strings that already own their text are moved.
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::Null => Value::Null,
+            BorrowedValue::Boolean(b) => Value::Boolean(b),
+            BorrowedValue::Number(n) => Value::Number(n),
+            BorrowedValue::String(s) => Value::String(s.into_owned()),
+            BorrowedValue::Array(arr) => {
+                Value::Array(arr.into_iter().map(BorrowedValue::into_owned).collect())
+            }
+            BorrowedValue::Object(obj) => {
+                let mut map = Map::with_capacity(obj.len());
+                // `append` keeps repeated keys from `DuplicateKeys::KeepAll`
+                for (k, v) in obj {
+                    map.append(k.into_owned(), v.into_owned());
+                }
+                Value::Object(map)
+            }
+        }
+    }
+
+    /// Drops the value without recursing, see [`Value::drop_iteratively`].
+    pub fn drop_iteratively(self) {
+        crate::drop_iteratively(Some(self));
+    }
+}
+
+impl<'a> From<BorrowedValue<'a>> for Value {
+    fn from(value: BorrowedValue<'a>) -> Self {
+        value.into_owned()
+    }
+}
+
+impl<'a> Tree<'a> for BorrowedValue<'a> {
+    type Key = Cow<'a, str>;
+
+    fn null() -> Self {
+        BorrowedValue::Null
+    }
+
+    fn boolean(b: bool) -> Self {
+        BorrowedValue::Boolean(b)
+    }
+
+    fn number(n: Number) -> Self {
+        BorrowedValue::Number(n)
+    }
+
+    fn string(s: Cow<'a, str>) -> Self {
+        BorrowedValue::String(s)
+    }
+
+    fn key(s: Cow<'a, str>) -> Self::Key {
+        s
+    }
+
+    fn array(items: Vec<Self>) -> Self {
+        BorrowedValue::Array(items)
+    }
+
+    fn object(map: Map<Self::Key, Self>) -> Self {
+        BorrowedValue::Object(map)
+    }
+
+    fn into_items(self) -> Vec<Self> {
+        match self {
+            BorrowedValue::Array(arr) => arr,
+            BorrowedValue::Object(obj) => obj.into_iter().map(|(_, v)| v).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[test]
+fn borrow_test() {
+    let text = r#"{"plain": "abc", "esc\naped": ["é", 1, null]}"#;
+    let value = crate::from_str_borrowed(text).unwrap();
+
+    match &value {
+        BorrowedValue::Object(obj) => {
+            let (key, plain) = obj.iter().next().unwrap();
+            assert!(matches!(key, Cow::Borrowed("plain")));
+            assert!(matches!(plain, BorrowedValue::String(Cow::Borrowed("abc"))));
+            assert!(obj.contains_key("esc\naped"));
+        }
+        _ => panic!("{:?}", value),
+    }
+
+    assert_eq!(value.into_owned(), crate::from_str(text).unwrap());
+}
+
+#[test]
+fn options_test() {
+    let opts = crate::ParseOptions {
+        duplicate_keys: crate::DuplicateKeys::KeepAll,
+        ..crate::ParseOptions::strict()
+    };
+    let value = opts.parse_borrowed(r#"{"a": 1, "a": 2}"#).unwrap();
+    assert_eq!(
+        Value::from(value),
+        opts.parse_str(r#"{"a": 1, "a": 2}"#).unwrap()
+    );
+
+    assert!(opts.parse_borrowed("\"a\u{1}\"").is_err());
+    assert!(crate::from_str_borrowed("\"a\u{1}\"").is_ok());
+}
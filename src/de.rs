@@ -0,0 +1,944 @@
+//! Deserializing Rust types from JSON text with [serde](https://serde.rs).
+//!
+//! The [`Deserializer`] runs the same nom parsers as [`from_str`](crate::from_str),
+//! so it accepts exactly the same input without building a [`Value`] first.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::str;
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::char;
+use nom::combinator::map;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::error::{RawError, RawKind};
+use crate::{
+    check_input_len, expect, js_key, js_number, js_spaces_and_comments, js_string, js_wtf8_string,
+    skip_spaces, DuplicateKeys, Error, Map, Number, PResult, ParseError, ParseOptions, Position,
+    Value,
+};
+
+/// Deserializes values straight from JSON text.
+pub struct Deserializer<'de> {
+    input: &'de str,
+    rest: &'de str,
+    opts: ParseOptions,
+    /// Arrays and objects that are currently open.
+    depth: usize,
+    /// Values so far, counting the one at the top.
+    nodes: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(input: &'de str) -> Self {
+        Deserializer::with_options(input, ParseOptions::default())
+    }
+
+    /// Parses with `opts` instead of the defaults.
+    ///
+    /// Of the [`duplicate_keys`](ParseOptions::duplicate_keys) policies, `Error` and
+    /// `FirstWins` are applied here. With `LastWins` and `KeepAll` every member is passed
+    /// on, and the type decides: [`Value`] and [`Map`] keep the last value of a repeated
+    /// key, a derived struct fails on a repeated field.
+    pub fn with_options(input: &'de str, opts: ParseOptions) -> Self {
+        Deserializer {
+            input,
+            rest: opts.skip_bom(input),
+            opts,
+            depth: 0,
+            nodes: 1,
+        }
+    }
+
+    /// Checks that only whitespace is left, call this after deserializing a value.
+    pub fn end(&mut self) -> Result<(), Error> {
+        self.skip_spaces();
+        if self.rest.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(RawError::expected(self.rest, "end of input")))
+        }
+    }
+
+    fn error(&self, raw: RawError) -> Error {
+        Error::Parse(ParseError::new(self.input, raw))
+    }
+
+    /// Adds the current position to errors raised by `Deserialize` impls.
+    fn fix_position(&self, err: Error) -> Error {
+        match err {
+            Error::Data {
+                message,
+                position: None,
+            } => Error::Data {
+                message,
+                position: Some(
+                    Position::START.advance(&self.input[..self.input.len() - self.rest.len()]),
+                ),
+            },
+            err => err,
+        }
+    }
+
+    fn parse<O>(&mut self, f: impl Fn(&'de str) -> PResult<'de, O>) -> Result<O, Error> {
+        // Everything is parsed through here, so this is as good as checking up front
+        check_input_len(self.input, 0, &self.opts).map_err(|e| self.error(e))?;
+        match f(self.rest) {
+            Ok((rest, o)) => {
+                self.rest = rest;
+                Ok(o)
+            }
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(self.error(e)),
+            Err(nom::Err::Incomplete(_)) => {
+                Err(self.error(RawError::expected(self.rest, "more input")))
+            }
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        self.rest = match js_spaces_and_comments(self.rest, &self.opts) {
+            Ok((rest, _)) => rest,
+            // Stop at an unterminated comment, whatever comes next fails on it
+            Err(_) => skip_spaces(self.rest, &self.opts),
+        };
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_spaces();
+        self.rest.chars().next()
+    }
+
+    /// Enters an array or object, enforcing [`ParseOptions::max_depth`].
+    fn open(&mut self, c: char) -> Result<(), Error> {
+        self.skip_spaces();
+        if let Some(max_depth) = self.opts.max_depth {
+            if self.depth >= max_depth {
+                let e = RawError::new(self.rest, RawKind::TooDeep { max_depth });
+                return Err(self.error(e));
+            }
+        }
+        self.parse(char(c))?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Checks the item of an array or object starting here, after `len` others,
+    /// against [`ParseOptions::max_items`].
+    fn count_item(&mut self, len: usize) -> Result<(), Error> {
+        self.skip_spaces();
+        match self.opts.max_items {
+            Some(max_items) if len >= max_items => {
+                let e = RawError::new(self.rest, RawKind::TooManyItems { max_items });
+                Err(self.error(e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts the value starting here, enforcing [`ParseOptions::max_nodes`].
+    fn count_node(&mut self) -> Result<(), Error> {
+        self.skip_spaces();
+        self.nodes += 1;
+        match self.opts.max_nodes {
+            Some(max_nodes) if self.nodes > max_nodes => {
+                let e = RawError::new(self.rest, RawKind::TooManyNodes { max_nodes });
+                Err(self.error(e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn close(&mut self, c: char) -> Result<(), Error> {
+        self.eat_char(c)?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn eat_char(&mut self, c: char) -> Result<(), Error> {
+        self.skip_spaces();
+        self.parse(char(c))?;
+        Ok(())
+    }
+
+    fn parse_string(&mut self) -> Result<String, Error> {
+        let opts = self.opts.clone();
+        self.parse(expect("string", |i| js_string(i, &opts)))
+    }
+
+    fn parse_key(&mut self) -> Result<String, Error> {
+        let opts = self.opts.clone();
+        self.parse(|i| js_key(i, &opts)).map(Cow::into_owned)
+    }
+
+    /// Whether `c` starts a string.
+    fn is_quote(&self, c: char) -> bool {
+        c == '"' || (self.opts.json5 && c == '\'')
+    }
+
+    fn parse_number(&mut self) -> Result<Number, Error> {
+        let opts = self.opts.clone();
+        self.parse(expect("value", |i| js_number(i, &opts)))
+    }
+
+    /// Numbers that only fit a decimal string (with `arbitrary_precision`) are passed
+    /// on as a map with their text under [`TOKEN`](crate::number::TOKEN), which the
+    /// visitor of [`Value`] turns back into a number.
+    fn visit_number<V: Visitor<'de>>(n: Number, visitor: V) -> Result<V::Value, Error> {
+        #[cfg(feature = "arbitrary_precision")]
+        {
+            if let Some(text) = n.as_decimal() {
+                return visitor.visit_map(DecimalAccess(Some(text.to_string())));
+            }
+        }
+        if let Some(n) = n.as_u64() {
+            visitor.visit_u64(n)
+        } else if let Some(n) = n.as_i64() {
+            visitor.visit_i64(n)
+        } else {
+            visitor.visit_f64(n.to_f64())
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let res = match self.peek() {
+            Some('n') => {
+                self.parse(tag("null"))?;
+                visitor.visit_unit()
+            }
+            Some('t') | Some('f') => {
+                let b = self.parse(alt((
+                    map(tag("true"), |_| true),
+                    map(tag("false"), |_| false),
+                )))?;
+                visitor.visit_bool(b)
+            }
+            Some(c) if self.is_quote(c) => {
+                let s = self.parse_string()?;
+                visitor.visit_string(s)
+            }
+            Some('[') => {
+                self.open('[')?;
+                let value = visitor.visit_seq(SeqAccess {
+                    de: &mut *self,
+                    len: 0,
+                })?;
+                self.close(']')?;
+                Ok(value)
+            }
+            Some('{') => {
+                self.open('{')?;
+                let value = visitor.visit_map(MapAccess {
+                    de: &mut *self,
+                    len: 0,
+                    keys: HashMap::new(),
+                })?;
+                self.close('}')?;
+                Ok(value)
+            }
+            _ => {
+                let n = self.parse_number()?;
+                Deserializer::visit_number(n, visitor)
+            }
+        };
+
+        res.map_err(|e| self.fix_position(e))
+    }
+
+    /// Numbers are rounded to the nearest `f64`, even those `arbitrary_precision` keeps
+    /// as text. Out of range ones fail, as without that feature.
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.peek() {
+            Some(c) if "[{ntf".contains(c) || self.is_quote(c) => self.deserialize_any(visitor),
+            _ => {
+                let start = self.rest;
+                let n = self.parse_number()?;
+                let f = n.to_f64();
+                if !f.is_finite() && n.is_finite() {
+                    return Err(self.error(RawError::new(start, RawKind::NumberOutOfRange)));
+                }
+                visitor.visit_f64(f).map_err(|e| self.fix_position(e))
+            }
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.peek() == Some('n') {
+            self.parse(tag("null"))?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Strings are decoded as WTF-8, so lone surrogates survive with
+    /// [`LoneSurrogates::Keep`](crate::LoneSurrogates::Keep). Anything else is handled
+    /// by the visitor, e.g. an array of numbers.
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.peek().is_some_and(|c| self.is_quote(c)) {
+            let opts = self.opts.clone();
+            let bytes = self.parse(|i| js_wtf8_string(i, &opts))?;
+            visitor
+                .visit_byte_buf(bytes)
+                .map_err(|e| self.fix_position(e))
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// Unit variants are plain strings, other variants an object with a single key.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.peek() {
+            Some(c) if self.is_quote(c) => {
+                let variant = self.parse_string()?;
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            Some('{') => {
+                self.open('{')?;
+                let value = visitor.visit_enum(EnumAccess { de: &mut *self })?;
+                self.close('}')?;
+                Ok(value)
+            }
+            _ => Err(self.error(RawError::expected(self.rest, "string or object"))),
+        }
+        .map_err(|e| self.fix_position(e))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    /// Items so far.
+    len: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.de.peek() == Some(']') {
+            return Ok(None);
+        }
+
+        if self.len > 0 {
+            self.de.eat_char(',')?;
+            if self.de.opts.allow_trailing_commas && self.de.peek() == Some(']') {
+                return Ok(None);
+            }
+        }
+        self.de.count_item(self.len)?;
+        self.de.count_node()?;
+        self.len += 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct MapAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    /// Items so far.
+    len: usize,
+    /// Where each key so far starts, if the duplicate key policy needs to know.
+    keys: HashMap<String, &'de str>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let key = loop {
+            if self.de.peek() == Some('}') {
+                return Ok(None);
+            }
+
+            if self.len > 0 {
+                self.de.eat_char(',')?;
+                if self.de.opts.allow_trailing_commas && self.de.peek() == Some('}') {
+                    return Ok(None);
+                }
+            }
+            self.de.count_item(self.len)?;
+            self.len += 1;
+
+            let start = self.de.rest;
+            let key = self.de.parse_key()?;
+            let policy = self.de.opts.duplicate_keys;
+            if let DuplicateKeys::LastWins | DuplicateKeys::KeepAll = policy {
+                break key;
+            }
+            match self.keys.get(&key) {
+                None => {
+                    self.keys.insert(key.clone(), start);
+                    break key;
+                }
+                Some(&first) if policy == DuplicateKeys::Error => {
+                    let mut e = RawError::new(start, RawKind::DuplicateKey { key, first });
+                    e.context.push("object");
+                    return Err(self.de.error(e));
+                }
+                // `FirstWins`: the visitor never sees the member
+                Some(_) => {
+                    self.de.eat_char(':')?;
+                    self.de.count_node()?;
+                    <de::IgnoredAny as de::Deserialize>::deserialize(&mut *self.de)?;
+                }
+            }
+        };
+        seed.deserialize(MapKey(key))
+            .map(Some)
+            .map_err(|e| self.de.fix_position(e))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        self.de.eat_char(':')?;
+        self.de.count_node()?;
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// A number kept as text, as a map with the text under [`TOKEN`](crate::number::TOKEN).
+#[cfg(feature = "arbitrary_precision")]
+struct DecimalAccess(Option<String>);
+
+#[cfg(feature = "arbitrary_precision")]
+impl<'de> de::MapAccess<'de> for DecimalAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.0 {
+            Some(_) => seed
+                .deserialize(MapKey(crate::number::TOKEN.to_string()))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let text = self.0.take().unwrap();
+        seed.deserialize(IntoDeserializer::<Error>::into_deserializer(text))
+    }
+}
+
+struct EnumAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        self.de.skip_spaces();
+        let variant = self.de.parse_key()?;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(variant))?;
+        self.de.eat_char(':')?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for EnumAccess<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// Object keys are always strings, but may stand for numbers, e.g. in a `HashMap<u32, _>`.
+struct MapKey(String);
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(n) => visitor.$visit(n),
+                    Err(_) => visitor.visit_string(self.0),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKey {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_bool => visit_bool,
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(self.0))
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Deserializes an instance of `T` from JSON text.
+///
+/// ```
+/// #[derive(serde::Deserialize)]
+/// struct Entry {
+///     id: u64,
+///     tags: Vec<String>,
+/// }
+///
+/// let entry: Entry = json_rs_prac::de::from_str(r#"{"id": 7, "tags": ["a"]}"#).unwrap();
+/// assert_eq!(entry.id, 7);
+/// ```
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    let mut de = Deserializer::new(s);
+    let value = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
+
+/// Deserializes an instance of `T` from UTF-8 encoded JSON.
+pub fn from_slice<T: DeserializeOwned>(v: &[u8]) -> Result<T, Error> {
+    from_str(&crate::encoding::decode(v, &ParseOptions::default())?)
+}
+
+/// Deserializes an instance of `T` from JSON read from `reader`.
+pub fn from_reader<T: DeserializeOwned>(mut reader: impl Read) -> Result<T, Error> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    from_slice(&buf)
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Data {
+            message: msg.to_string(),
+            position: None,
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, d: D) -> Result<Value, D::Error> {
+        de::Deserialize::deserialize(d)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(b))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+        Ok(Value::Number(n.into()))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::Number(n.into()))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(n).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut arr = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            arr.push(item);
+        }
+        Ok(Value::Array(arr))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut map = Map::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some(k) = access.next_key::<String>()? {
+            #[cfg(feature = "arbitrary_precision")]
+            {
+                // A number kept as text, see `Deserializer::visit_number`
+                if map.is_empty() && k == crate::number::TOKEN {
+                    let text: String = access.next_value()?;
+                    return text.parse().map(Value::Number).map_err(de::Error::custom);
+                }
+            }
+            map.insert(k, access.next_value()?);
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Value, D::Error> {
+        d.deserialize_any(ValueVisitor)
+    }
+}
+
+impl<'de, K, V> de::Deserialize<'de> for Map<K, V>
+where
+    K: de::Deserialize<'de> + Hash + Eq + Clone,
+    V: de::Deserialize<'de>,
+{
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct MapVisitor<K, V>(PhantomData<(K, V)>);
+
+        impl<'de, K, V> Visitor<'de> for MapVisitor<K, V>
+        where
+            K: de::Deserialize<'de> + Hash + Eq + Clone,
+            V: de::Deserialize<'de>,
+        {
+            type Value = Map<K, V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Map<K, V>, A::Error> {
+                let mut map = Map::with_capacity(access.size_hint().unwrap_or(0));
+                while let Some((k, v)) = access.next_entry()? {
+                    map.insert(k, v);
+                }
+                Ok(map)
+            }
+        }
+
+        d.deserialize_map(MapVisitor(PhantomData))
+    }
+}
+
+#[test]
+fn struct_test() {
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Plain,
+        Sized(u32),
+        Point { x: i8, y: i8 },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        id: u64,
+        ratio: Option<f64>,
+        kinds: Vec<Kind>,
+        counts: HashMap<u32, bool>,
+    }
+
+    let config: Config = from_str(
+        r#"{
+            "name": "a\nb",
+            "id": 18446744073709551615,
+            "ratio": null,
+            "kinds": ["Plain", {"Sized": 3}, {"Point": {"x": -1, "y": 2}}],
+            "counts": {"1": true}
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config,
+        Config {
+            name: "a\nb".into(),
+            id: u64::MAX,
+            ratio: None,
+            kinds: vec![Kind::Plain, Kind::Sized(3), Kind::Point { x: -1, y: 2 }],
+            counts: vec![(1, true)].into_iter().collect(),
+        }
+    );
+}
+
+#[test]
+fn error_test() {
+    match from_str::<Vec<u8>>("[1,\n 256]") {
+        Err(Error::Data { position, .. }) => assert_eq!(position.map(|p| p.line), Some(2)),
+        res => panic!("{:?}", res),
+    }
+
+    assert!(matches!(from_str::<Vec<u8>>("[1, 2"), Err(Error::Parse(_))));
+    assert!(matches!(
+        from_str::<Vec<u8>>("[1, 2] 3"),
+        Err(Error::Parse(_))
+    ));
+}
+
+#[test]
+fn depth_test() {
+    let deep = "[".repeat(200) + &"]".repeat(200);
+    match from_str::<Value>(&deep) {
+        Err(Error::Parse(e)) => assert_eq!(e.column(), 129),
+        res => panic!("{:?}", res),
+    }
+
+    let opts = ParseOptions {
+        max_depth: Some(200),
+        ..Default::default()
+    };
+    let mut de = Deserializer::with_options(&deep, opts);
+    de::Deserialize::deserialize(&mut de)
+        .map(|_: Value| ())
+        .unwrap();
+}
+
+#[test]
+fn value_test() {
+    let text = include_str!("../example.json");
+    assert_eq!(
+        from_str::<Value>(text).unwrap(),
+        crate::from_str(text).unwrap()
+    );
+}
+
+#[test]
+fn limits_test() {
+    let opts = ParseOptions {
+        max_items: Some(2),
+        max_nodes: Some(6),
+        ..Default::default()
+    };
+    let parse = |text| {
+        let mut de = Deserializer::with_options(text, opts.clone());
+        de::Deserialize::deserialize(&mut de).map_err(|e| match e {
+            Error::Parse(e) => (e.kind().clone(), e.offset()),
+            e => panic!("{}", e),
+        })
+    };
+
+    assert_eq!(parse("[[1, 2], [3]]"), Ok(vec![vec![1u8, 2], vec![3]]));
+    assert_eq!(
+        parse("[[1, 2, 3]]"),
+        Err((crate::ParseErrorKind::TooManyItems { max_items: 2 }, 8))
+    );
+    assert_eq!(
+        parse("[[1, 2], [3, 4]]"),
+        Err((crate::ParseErrorKind::TooManyNodes { max_nodes: 6 }, 13))
+    );
+}
+
+#[test]
+fn jsonc_test() {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Settings {
+        tabs: bool,
+        rulers: Vec<u32>,
+    }
+
+    let text = "{\n  // Editor\n  \"tabs\": false,\n  \"rulers\": [80, /* 100, */ 120,],\n}";
+    let mut de = Deserializer::with_options(text, ParseOptions::jsonc());
+    let settings = Settings::deserialize(&mut de).unwrap();
+    de.end().unwrap();
+    assert_eq!(
+        settings,
+        Settings {
+            tabs: false,
+            rulers: vec![80, 120],
+        }
+    );
+
+    assert!(from_str::<Settings>(text).is_err());
+    let mut de = Deserializer::with_options("[1, /* 2", ParseOptions::jsonc());
+    assert!(Vec::<u8>::deserialize(&mut de).is_err());
+}
+
+#[test]
+fn json5_test() {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Plain,
+        Sized(u32),
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Entry {
+        name: String,
+        mask: u8,
+        ratio: f64,
+        kinds: Vec<Kind>,
+    }
+
+    let text = "{name: 'a\\\nb', mask: 0xff, ratio: -Infinity, kinds: ['Plain', {Sized: 3},],}";
+    let mut de = Deserializer::with_options(text, ParseOptions::json5());
+    let entry = Entry::deserialize(&mut de).unwrap();
+    de.end().unwrap();
+    assert_eq!(
+        entry,
+        Entry {
+            name: "ab".into(),
+            mask: 255,
+            ratio: f64::NEG_INFINITY,
+            kinds: vec![Kind::Plain, Kind::Sized(3)],
+        }
+    );
+}
+
+#[test]
+fn duplicate_keys_test() {
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Entry {
+        a: Vec<u32>,
+        b: u32,
+    }
+
+    let text = "{\"a\": [1], \"b\": 2,\n \"a\": [3, {\"a\": 4}]}";
+    let with = |duplicate_keys| ParseOptions {
+        duplicate_keys,
+        ..Default::default()
+    };
+    let value = |opts: &ParseOptions| {
+        let mut de = Deserializer::with_options(text, opts.clone());
+        Value::deserialize(&mut de).map_err(|e| e.to_string())
+    };
+
+    let opts = with(DuplicateKeys::Error);
+    assert_eq!(
+        value(&opts).unwrap_err(),
+        opts.parse_str(text).unwrap_err().to_string()
+    );
+    let mut de = Deserializer::with_options(text, opts);
+    assert!(HashMap::<String, Value>::deserialize(&mut de).is_err());
+
+    let opts = with(DuplicateKeys::FirstWins);
+    assert_eq!(value(&opts).unwrap(), opts.parse_str(text).unwrap());
+    let mut de = Deserializer::with_options(text, opts);
+    let entry = Entry::deserialize(&mut de).unwrap();
+    assert_eq!(entry, Entry { a: vec![1], b: 2 });
+    de.end().unwrap();
+
+    let opts = with(DuplicateKeys::LastWins);
+    assert_eq!(value(&opts).unwrap(), opts.parse_str(text).unwrap());
+}
+
+#[test]
+fn float_test() {
+    assert_eq!(from_str::<f64>("0.5").unwrap(), 0.5);
+    assert_eq!(from_str::<Vec<f32>>("[1, -2.5]").unwrap(), vec![1.0, -2.5]);
+    assert!(from_str::<f64>("\"1\"").is_err());
+
+    let pi = "3.14159265358979323846264338327950288";
+    assert_eq!(from_str::<f64>(pi).unwrap(), std::f64::consts::PI);
+    match from_str::<f64>("1e400") {
+        Err(Error::Parse(e)) => assert_eq!(e.kind(), &crate::ParseErrorKind::NumberOutOfRange),
+        res => panic!("{:?}", res),
+    }
+
+    // `Value` keeps what `arbitrary_precision` keeps
+    let text = format!("[{},1e400]", pi);
+    let value: Value = from_str(&text).unwrap();
+    assert_eq!(value, crate::from_str(&text).unwrap());
+}
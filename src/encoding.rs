@@ -0,0 +1,286 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
+
+use crate::error::{RawError, RawKind};
+use crate::{Error, ParseError, ParseOptions};
+
+/// Character encoding of a JSON text given as bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    /// Tells the encoding of `bytes` from its byte order mark, or without one from where
+    /// the first characters have zero bytes, as in RFC 4627 section 3. That works because
+    /// a JSON text starts with ASCII.
+    ///
+    /// ```
+    /// use json_rs_prac::Encoding;
+    ///
+    /// assert_eq!(Encoding::detect(b"[1]"), Encoding::Utf8);
+    /// assert_eq!(Encoding::detect(b"[\x001\x00]\x00"), Encoding::Utf16Le);
+    /// assert_eq!(Encoding::detect(b"\x00\x00\x00["), Encoding::Utf32Be);
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        match bytes {
+            [0xef, 0xbb, 0xbf, ..] => Encoding::Utf8,
+            [0x00, 0x00, 0xfe, 0xff, ..] => Encoding::Utf32Be,
+            [0xff, 0xfe, 0x00, 0x00, ..] => Encoding::Utf32Le,
+            [0xfe, 0xff, ..] => Encoding::Utf16Be,
+            [0xff, 0xfe, ..] => Encoding::Utf16Le,
+            [0x00, 0x00, 0x00, _, ..] => Encoding::Utf32Be,
+            [_, 0x00, 0x00, 0x00, ..] => Encoding::Utf32Le,
+            [0x00, _, 0x00, _, ..] | [0x00, _] => Encoding::Utf16Be,
+            [_, 0x00, _, 0x00, ..] | [_, 0x00] => Encoding::Utf16Le,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /// Bytes per code unit.
+    fn unit_len(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        })
+    }
+}
+
+/// How far decoding got.
+#[derive(PartialEq)]
+enum End {
+    Complete,
+    /// The input stops in the middle of a character.
+    Cut,
+    Invalid,
+}
+
+/// Decodes `bytes` up to the first invalid or incomplete character.
+fn decode_prefix(bytes: &[u8], encoding: Encoding) -> (Cow<'_, str>, End) {
+    let units = bytes.chunks_exact(encoding.unit_len());
+    let rest = units.remainder().len();
+
+    let (text, end) = match encoding {
+        Encoding::Utf8 => {
+            return match str::from_utf8(bytes) {
+                Ok(text) => (Cow::Borrowed(text), End::Complete),
+                Err(e) => {
+                    let valid = str::from_utf8(&bytes[..e.valid_up_to()]).unwrap();
+                    let end = match e.error_len() {
+                        Some(_) => End::Invalid,
+                        None => End::Cut,
+                    };
+                    (Cow::Borrowed(valid), end)
+                }
+            }
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = units.map(|unit| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([unit[0], unit[1]]),
+                _ => u16::from_be_bytes([unit[0], unit[1]]),
+            });
+            let mut text = String::with_capacity(bytes.len() / 2);
+            let mut end = End::Complete;
+            let mut decoded = 0;
+            for c in std::char::decode_utf16(units) {
+                match c {
+                    Ok(c) => {
+                        text.push(c);
+                        decoded += c.len_utf16() * 2;
+                    }
+                    Err(e) => {
+                        // A high surrogate at the very end may be completed by what was cut off
+                        let high = (0xd800..0xdc00).contains(&e.unpaired_surrogate());
+                        end = if high && decoded + 2 == bytes.len() - rest {
+                            End::Cut
+                        } else {
+                            End::Invalid
+                        };
+                        break;
+                    }
+                }
+            }
+            (text, end)
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            let mut text = String::with_capacity(bytes.len() / 4);
+            let mut end = End::Complete;
+            for unit in units {
+                let unit = [unit[0], unit[1], unit[2], unit[3]];
+                let u = match encoding {
+                    Encoding::Utf32Le => u32::from_le_bytes(unit),
+                    _ => u32::from_be_bytes(unit),
+                };
+                match std::char::from_u32(u) {
+                    Some(c) => text.push(c),
+                    None => {
+                        end = End::Invalid;
+                        break;
+                    }
+                }
+            }
+            (text, end)
+        }
+    };
+
+    match end {
+        End::Complete if rest > 0 => (Cow::Owned(text), End::Cut),
+        end => (Cow::Owned(text), end),
+    }
+}
+
+/// Turns the bytes given to [`ParseOptions::parse_slice`] into text, enforcing
+/// [`ParseOptions::max_input_len`] on the bytes themselves.
+pub(crate) fn decode<'a>(bytes: &'a [u8], opts: &ParseOptions) -> Result<Cow<'a, str>, Error> {
+    let encoding = if opts.detect_encoding {
+        Encoding::detect(bytes)
+    } else {
+        Encoding::Utf8
+    };
+
+    let (head, max_input_len) = match opts.max_input_len {
+        Some(max_input_len) if bytes.len() > max_input_len => {
+            (&bytes[..max_input_len], Some(max_input_len))
+        }
+        _ => (bytes, None),
+    };
+
+    let (text, end) = decode_prefix(head, encoding);
+    let kind = match (end, max_input_len) {
+        (End::Complete, None) => return Ok(text),
+        // Only the part up to the limit has to be valid to tell where it is
+        (End::Complete, Some(max_input_len)) | (End::Cut, Some(max_input_len)) => {
+            RawKind::InputTooLong { max_input_len }
+        }
+        _ => RawKind::InvalidEncoding { encoding },
+    };
+
+    let e = RawError::new(&text[text.len()..], kind);
+    Err(ParseError::new(&text, e).into())
+}
+
+#[test]
+fn detect_test() {
+    let text = "{\"\u{e9}\u{1f600}\": [1]}";
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    let utf32: Vec<u32> = text.chars().map(u32::from).collect();
+
+    let cases: Vec<(Encoding, Vec<u8>)> = vec![
+        (Encoding::Utf8, text.as_bytes().to_vec()),
+        (
+            Encoding::Utf16Le,
+            utf16
+                .iter()
+                .flat_map(|u| u.to_le_bytes().to_vec())
+                .collect(),
+        ),
+        (
+            Encoding::Utf16Be,
+            utf16
+                .iter()
+                .flat_map(|u| u.to_be_bytes().to_vec())
+                .collect(),
+        ),
+        (
+            Encoding::Utf32Le,
+            utf32
+                .iter()
+                .flat_map(|u| u.to_le_bytes().to_vec())
+                .collect(),
+        ),
+        (
+            Encoding::Utf32Be,
+            utf32
+                .iter()
+                .flat_map(|u| u.to_be_bytes().to_vec())
+                .collect(),
+        ),
+    ];
+
+    let opts = ParseOptions {
+        detect_encoding: true,
+        ..Default::default()
+    };
+    for (encoding, bytes) in cases {
+        assert_eq!(Encoding::detect(&bytes), encoding);
+        assert_eq!(decode(&bytes, &opts).unwrap(), text);
+
+        if encoding == Encoding::Utf8 {
+            continue;
+        }
+        // Cut off in the middle of the last character
+        match decode(&bytes[..bytes.len() - 1], &opts) {
+            Err(Error::Parse(e)) => {
+                assert_eq!(
+                    e.kind(),
+                    &crate::ParseErrorKind::InvalidEncoding { encoding }
+                );
+                assert_eq!(e.offset(), text.len() - 1);
+            }
+            res => panic!("{} {:?}", encoding, res),
+        }
+    }
+
+    assert_eq!(Encoding::detect(b"1\x00"), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(b"\xff\xfe1\x00"), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(b"1"), Encoding::Utf8);
+}
+
+#[test]
+fn invalid_test() {
+    let opts = ParseOptions {
+        detect_encoding: true,
+        ..Default::default()
+    };
+
+    // A lone surrogate in the middle
+    let bytes = b"[\x00\"\x00\x00\xd8a\x00\"\x00]\x00";
+    match decode(bytes, &opts) {
+        Err(Error::Parse(e)) => {
+            assert_eq!(
+                e.kind(),
+                &crate::ParseErrorKind::InvalidEncoding {
+                    encoding: Encoding::Utf16Le
+                }
+            );
+            assert_eq!(e.offset(), 2);
+        }
+        res => panic!("{:?}", res),
+    }
+
+    let opts = ParseOptions {
+        max_input_len: Some(5),
+        ..opts
+    };
+    match decode(b"[\"\xc3\xa9\xff\"]", &opts) {
+        Err(Error::Parse(e)) => assert_eq!(e.to_string(), "invalid UTF-8 at line 1, column 4"),
+        res => panic!("{:?}", res),
+    }
+    match decode(b"[\"\xc3\xa9\xc3\xa9\"]", &opts) {
+        Err(Error::Parse(e)) => {
+            assert_eq!(
+                e.to_string(),
+                "input longer than 5 bytes at line 1, column 4"
+            )
+        }
+        res => panic!("{:?}", res),
+    }
+}
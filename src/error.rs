@@ -0,0 +1,636 @@
+use std::fmt;
+use std::io;
+
+use nom::error::ErrorKind;
+
+use crate::Encoding;
+
+/// Error produced by the nom parsers while walking the input.
+///
+/// It only knows the remaining input, [`ParseError::new`] turns it into a position
+/// within the whole document.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RawError<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) kind: RawKind<'a>,
+    pub(crate) expected: Vec<&'static str>,
+    pub(crate) context: Vec<&'static str>,
+}
+
+/// [`ParseErrorKind`] with positions still pointing into the input.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RawKind<'a> {
+    Syntax,
+    DuplicateKey {
+        key: String,
+        first: &'a str,
+    },
+    /// Same as `DuplicateKey`, for when the first key is no longer part of the input.
+    DuplicateKeyAt {
+        key: String,
+        first: Position,
+    },
+    TooDeep {
+        max_depth: usize,
+    },
+    InputTooLong {
+        max_input_len: usize,
+    },
+    StringTooLong {
+        max_string_len: usize,
+    },
+    NumberTooLong {
+        max_number_digits: usize,
+    },
+    NumberOutOfRange,
+    TooManyItems {
+        max_items: usize,
+    },
+    TooManyNodes {
+        max_nodes: usize,
+    },
+    InvalidEncoding {
+        encoding: Encoding,
+    },
+}
+
+impl<'a> RawError<'a> {
+    pub(crate) fn expected(input: &'a str, label: &'static str) -> Self {
+        RawError {
+            input,
+            kind: RawKind::Syntax,
+            expected: vec![label],
+            context: Vec::new(),
+        }
+    }
+
+    pub(crate) fn new(input: &'a str, kind: RawKind<'a>) -> Self {
+        RawError {
+            input,
+            kind,
+            expected: Vec::new(),
+            context: Vec::new(),
+        }
+    }
+}
+
+fn describe_kind(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::Eof => "end of input",
+        ErrorKind::Digit => "digit",
+        ErrorKind::HexDigit | ErrorKind::TakeWhileMN => "hex digit",
+        ErrorKind::Float => "number",
+        _ => "valid JSON",
+    }
+}
+
+fn describe_char(c: char) -> &'static str {
+    match c {
+        '"' => "'\"'",
+        ',' => "','",
+        ':' => "':'",
+        '[' => "'['",
+        ']' => "']'",
+        '{' => "'{'",
+        '}' => "'}'",
+        '\\' => "'\\'",
+        '-' => "'-'",
+        '.' => "'.'",
+        'u' => "'u'",
+        _ => "character",
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for RawError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        RawError::expected(input, describe_kind(kind))
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: &'a str, c: char) -> Self {
+        RawError::expected(input, describe_char(c))
+    }
+
+    /// Keeps whichever error got further into the input, merging the expectations of
+    /// errors at the same position.
+    fn or(mut self, other: Self) -> Self {
+        if other.input.len() < self.input.len() {
+            other
+        } else if other.input.len() > self.input.len() {
+            self
+        } else {
+            for label in other.expected {
+                if !self.expected.contains(&label) {
+                    self.expected.push(label);
+                }
+            }
+            self
+        }
+    }
+
+    fn add_context(_: &'a str, ctx: &'static str, mut other: Self) -> Self {
+        other.context.push(ctx);
+        other
+    }
+}
+
+/// A location in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, counted in characters.
+    pub column: usize,
+}
+
+impl Position {
+    /// The start of a document.
+    pub(crate) const START: Position = Position {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    /// The position right after `text`, if it starts at this position.
+    pub(crate) fn advance(self, text: &str) -> Self {
+        let offset = self.offset + text.len();
+        match text.rfind('\n') {
+            Some(pos) => Position {
+                offset,
+                line: self.line + text.matches('\n').count(),
+                column: text[pos + 1..].chars().count() + 1,
+            },
+            None => Position {
+                offset,
+                line: self.line,
+                column: self.column + text.chars().count(),
+            },
+        }
+    }
+
+    /// Like [`advance`](Position::advance) for UTF-8 that may be cut anywhere, as in
+    /// chunks of a stream.
+    pub(crate) fn advance_bytes(self, bytes: &[u8]) -> Self {
+        let chars = |bytes: &[u8]| bytes.iter().filter(|&&b| b & 0xc0 != 0x80).count();
+        let offset = self.offset + bytes.len();
+        match bytes.iter().rposition(|&b| b == b'\n') {
+            Some(pos) => Position {
+                offset,
+                line: self.line + bytes.iter().filter(|&&b| b == b'\n').count(),
+                column: chars(&bytes[pos + 1..]) + 1,
+            },
+            None => Position {
+                offset,
+                line: self.line,
+                column: self.column + chars(bytes),
+            },
+        }
+    }
+
+    /// Turns a position in a text that starts at `base` into one in the whole document.
+    pub(crate) fn rebase(self, base: Position) -> Self {
+        Position {
+            offset: base.offset + self.offset,
+            line: base.line + self.line - 1,
+            column: if self.line == 1 {
+                base.column + self.column - 1
+            } else {
+                self.column
+            },
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// What went wrong in a [`ParseError`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// The input doesn't follow the JSON grammar, see [`ParseError::expected`].
+    Syntax,
+    /// An object has the same key twice and
+    /// [`DuplicateKeys::Error`](crate::DuplicateKeys::Error) was requested.
+    /// The error itself points at the second occurrence.
+    DuplicateKey { key: String, first: Position },
+    /// Arrays and objects are nested deeper than
+    /// [`max_depth`](crate::ParseOptions::max_depth) allows. The error points at the
+    /// first `[` or `{` that is too deep.
+    TooDeep { max_depth: usize },
+    /// The input is longer than [`max_input_len`](crate::ParseOptions::max_input_len)
+    /// bytes. The error points at the first byte past the limit.
+    InputTooLong { max_input_len: usize },
+    /// A string or key is longer than
+    /// [`max_string_len`](crate::ParseOptions::max_string_len) once unescaped. The error
+    /// points at its opening quote.
+    StringTooLong { max_string_len: usize },
+    /// A number has more digits than
+    /// [`max_number_digits`](crate::ParseOptions::max_number_digits). The error points
+    /// at its first character.
+    NumberTooLong { max_number_digits: usize },
+    /// A number is too large in magnitude for `f64`, like `1e400`. Only without the
+    /// `arbitrary_precision` feature, which keeps such numbers as text. The error points
+    /// at its first character.
+    NumberOutOfRange,
+    /// An array has more elements or an object more members than
+    /// [`max_items`](crate::ParseOptions::max_items). The error points at the first one
+    /// too many.
+    TooManyItems { max_items: usize },
+    /// The document has more values than [`max_nodes`](crate::ParseOptions::max_nodes).
+    /// The error points at the first one too many.
+    TooManyNodes { max_nodes: usize },
+    /// The input has a byte sequence that isn't valid in its encoding, or stops in the
+    /// middle of a character. The error points at the first character that couldn't be
+    /// decoded.
+    InvalidEncoding { encoding: Encoding },
+}
+
+/// Error returned when the input is not valid JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    inner: Box<Inner>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Inner {
+    kind: ParseErrorKind,
+    offset: usize,
+    line: usize,
+    column: usize,
+    expected: Vec<&'static str>,
+    found: String,
+    context: Vec<&'static str>,
+    /// The part of the offending line around the error.
+    source_line: String,
+    /// Characters of `source_line` before the error.
+    caret: usize,
+}
+
+/// Characters of the offending line kept on either side of the error, so that the
+/// error of a minified document doesn't hold all of it.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// The part of `line` around byte `at`, with `…` where it was cut, and the number of
+/// characters in it before `at`.
+fn window(line: &str, at: usize) -> (String, usize) {
+    let at = at.min(line.len());
+    let (before, after) = line.split_at(at);
+
+    let skip = before.chars().count().saturating_sub(SNIPPET_CONTEXT);
+    let mut window = String::new();
+    if skip > 0 {
+        window.push('\u{2026}');
+    }
+    window.extend(before.chars().skip(skip));
+    let caret = window.chars().count();
+
+    let mut after = after.chars();
+    window.extend(after.by_ref().take(SNIPPET_CONTEXT));
+    if after.next().is_some() {
+        window.push('\u{2026}');
+    }
+    (window, caret)
+}
+
+impl ParseError {
+    /// Locates `raw` within `input`, which must be the whole text that was given to the parser.
+    pub(crate) fn new(input: &str, raw: RawError) -> Self {
+        ParseError::with_base(input, raw, Position::START)
+    }
+
+    /// Like [`new`](ParseError::new) for parsers that dropped everything before `base`,
+    /// so `input` starts there.
+    pub(crate) fn with_base(input: &str, raw: RawError, base: Position) -> Self {
+        let relative = input.len() - raw.input.len();
+        let Position {
+            offset,
+            line,
+            column,
+        } = base.advance(&input[..relative]);
+        let line_start = input[..relative].rfind('\n').map_or(0, |pos| pos + 1);
+        let line_end = raw
+            .input
+            .find('\n')
+            .map_or(input.len(), |pos| relative + pos);
+
+        let (source_line, caret) = window(
+            input[line_start..line_end].trim_end_matches('\r'),
+            relative - line_start,
+        );
+
+        let kind = match raw.kind {
+            RawKind::Syntax => ParseErrorKind::Syntax,
+            RawKind::DuplicateKey { key, first } => ParseErrorKind::DuplicateKey {
+                key,
+                first: base.advance(&input[..input.len() - first.len()]),
+            },
+            RawKind::DuplicateKeyAt { key, first } => ParseErrorKind::DuplicateKey { key, first },
+            RawKind::TooDeep { max_depth } => ParseErrorKind::TooDeep { max_depth },
+            RawKind::InputTooLong { max_input_len } => {
+                ParseErrorKind::InputTooLong { max_input_len }
+            }
+            RawKind::StringTooLong { max_string_len } => {
+                ParseErrorKind::StringTooLong { max_string_len }
+            }
+            RawKind::NumberTooLong { max_number_digits } => {
+                ParseErrorKind::NumberTooLong { max_number_digits }
+            }
+            RawKind::NumberOutOfRange => ParseErrorKind::NumberOutOfRange,
+            RawKind::TooManyItems { max_items } => ParseErrorKind::TooManyItems { max_items },
+            RawKind::TooManyNodes { max_nodes } => ParseErrorKind::TooManyNodes { max_nodes },
+            RawKind::InvalidEncoding { encoding } => ParseErrorKind::InvalidEncoding { encoding },
+        };
+
+        let inner = Inner {
+            kind,
+            offset,
+            line,
+            column,
+            expected: raw.expected,
+            found: describe_found(raw.input),
+            context: raw.context,
+            source_line,
+            caret,
+        };
+
+        ParseError {
+            inner: Box::new(inner),
+        }
+    }
+
+    /// Moves an error in a text that starts at `base` into the document around it.
+    pub(crate) fn rebase(mut self, base: Position) -> Self {
+        let Position {
+            offset,
+            line,
+            column,
+        } = self.position().rebase(base);
+        self.inner.offset = offset;
+        self.inner.line = line;
+        self.inner.column = column;
+        if let ParseErrorKind::DuplicateKey { first, .. } = &mut self.inner.kind {
+            *first = first.rebase(base);
+        }
+        self
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.inner.kind
+    }
+
+    /// Byte offset of the error in the input.
+    pub fn offset(&self) -> usize {
+        self.inner.offset
+    }
+
+    /// 1-based line number of the error.
+    pub fn line(&self) -> usize {
+        self.inner.line
+    }
+
+    /// 1-based column of the error, counted in characters.
+    pub fn column(&self) -> usize {
+        self.inner.column
+    }
+
+    pub fn position(&self) -> Position {
+        Position {
+            offset: self.inner.offset,
+            line: self.inner.line,
+            column: self.inner.column,
+        }
+    }
+
+    /// What the parser would have accepted at this position.
+    pub fn expected(&self) -> &[&'static str] {
+        &self.inner.expected
+    }
+
+    /// What was there instead.
+    pub fn found(&self) -> &str {
+        &self.inner.found
+    }
+
+    /// What was being parsed when the error happened, innermost first.
+    pub fn context(&self) -> &[&'static str] {
+        &self.inner.context
+    }
+
+    /// Renders the offending line with a caret under the error position, leaving out
+    /// what is more than 40 characters away from it:
+    ///
+    /// ```text
+    ///  --> 2:8
+    ///   |
+    /// 2 |   "a": tru
+    ///   |        ^ expected value
+    /// ```
+    pub fn snippet(&self) -> String {
+        let number = self.inner.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // Keep tabs so the caret lines up with the source line in a terminal
+        let pad: String = self
+            .inner
+            .source_line
+            .chars()
+            .take(self.inner.caret)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "{gutter}--> {line}:{column}\n{gutter} |\n{number} | {source}\n{gutter} | {pad}^ {message}",
+            gutter = gutter,
+            line = self.inner.line,
+            column = self.inner.column,
+            number = number,
+            source = self.inner.source_line,
+            pad = pad,
+            message = self.message(),
+        )
+    }
+
+    /// Short description of the error without its position.
+    fn message(&self) -> String {
+        match &self.inner.kind {
+            ParseErrorKind::Syntax => format!("expected {}", ExpectedList(&self.inner.expected)),
+            ParseErrorKind::DuplicateKey { key, first } => {
+                let mut quoted = String::new();
+                crate::write::write_string(&mut quoted, key).unwrap();
+                format!("duplicate key {}, first defined at {}", quoted, first)
+            }
+            ParseErrorKind::TooDeep { max_depth } => {
+                format!("nesting deeper than {} levels", max_depth)
+            }
+            ParseErrorKind::InputTooLong { max_input_len } => {
+                format!("input longer than {} bytes", max_input_len)
+            }
+            ParseErrorKind::StringTooLong { max_string_len } => {
+                format!("string longer than {} bytes", max_string_len)
+            }
+            ParseErrorKind::NumberTooLong { max_number_digits } => {
+                format!("number with more than {} digits", max_number_digits)
+            }
+            ParseErrorKind::NumberOutOfRange => "number out of range".to_string(),
+            ParseErrorKind::TooManyItems { max_items } => format!("more than {} items", max_items),
+            ParseErrorKind::TooManyNodes { max_nodes } => {
+                format!("more than {} values", max_nodes)
+            }
+            ParseErrorKind::InvalidEncoding { encoding } => format!("invalid {}", encoding),
+        }
+    }
+}
+
+fn describe_found(rest: &str) -> String {
+    let word: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric())
+        .take(16)
+        .collect();
+
+    match rest.chars().next() {
+        None => "end of input".to_string(),
+        Some(_) if !word.is_empty() => format!("`{}`", word),
+        Some(c) => format!("{:?}", c),
+    }
+}
+
+struct ExpectedList<'a>(&'a [&'static str]);
+
+impl fmt::Display for ExpectedList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            [] => f.write_str("valid JSON"),
+            [only] => f.write_str(only),
+            [init @ .., last] => write!(f, "{} or {}", init.join(", "), last),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.inner.kind {
+            ParseErrorKind::Syntax => write!(
+                f,
+                "{}, found {} at {}",
+                self.message(),
+                self.inner.found,
+                self.position()
+            )?,
+            _ => write!(f, "{} at {}", self.message(), self.position())?,
+        }
+
+        if let Some(ctx) = self.inner.context.first() {
+            write!(f, " in {}", ctx)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Error returned by [`from_str`](crate::from_str) and friends.
+#[derive(Debug)]
+pub enum Error {
+    /// The input is not valid JSON.
+    Parse(ParseError),
+    /// Reading the input failed.
+    Io(io::Error),
+    /// A `serde` implementation rejected the data, e.g. a number out of range for its
+    /// field. The position is known when the error came up while parsing.
+    Data {
+        message: String,
+        position: Option<Position>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(e) => e.fmt(f),
+            Error::Io(e) => e.fmt(f),
+            Error::Data {
+                message,
+                position: Some(position),
+            } => write!(f, "{} at {}", message, position),
+            Error::Data { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Data { .. } => None,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[test]
+fn position_test() {
+    let input = "{\n  \"a\": [1,\n\t2 3]\n}";
+    let raw = RawError::expected(&input[input.find('3').unwrap()..], "','");
+    let err = ParseError::new(input, raw);
+
+    assert_eq!(err.offset(), 16);
+    assert_eq!(err.line(), 3);
+    assert_eq!(err.column(), 4);
+    assert_eq!(err.found(), "`3`");
+    assert_eq!(
+        err.snippet(),
+        " --> 3:4\n  |\n3 | \t2 3]\n  | \t  ^ expected ','"
+    );
+}
+
+#[test]
+fn display_test() {
+    let input = "[1 2]";
+    let mut raw = RawError::expected(&input[3..], "','");
+    raw.expected.push("']'");
+    raw.context.push("array");
+    let err = ParseError::new(input, raw);
+
+    assert_eq!(
+        err.to_string(),
+        "expected ',' or ']', found `2` at line 1, column 4 in array"
+    );
+}
+
+#[test]
+fn long_line_test() {
+    let input = format!("[{}1 2{}]", "1,".repeat(100), ",3".repeat(100));
+    let err = ParseError::new(&input, RawError::expected(&input[203..], "','"));
+
+    assert_eq!(err.column(), 204);
+    let source = format!("\u{2026}{}1 2{},\u{2026}", "1,".repeat(19), ",3".repeat(19));
+    assert_eq!(
+        err.snippet(),
+        format!(
+            " --> 1:204\n  |\n1 | {}\n  | {}^ expected ','",
+            source,
+            " ".repeat(41)
+        )
+    );
+}
@@ -1,43 +1,390 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp;
+use std::hash::Hash;
+use std::io::Read;
 
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_while, take_while_m_n};
-use nom::character::complete::{char, none_of, one_of};
-use nom::combinator::map;
-use nom::error::{context, ParseError};
-use nom::multi::{many0, separated_list};
-use nom::number::complete::float;
-use nom::sequence::{delimited, preceded, separated_pair, terminated};
+use nom::character::complete::{anychar, char, digit0, digit1, hex_digit1, one_of};
+use nom::combinator::{all_consuming, cut, map, map_opt, not, opt, recognize, verify};
+use nom::multi::many0;
+use nom::number::complete::recognize_float;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 use nom::{AsChar, IResult, InputTakeAtPosition};
 use std::convert::TryInto;
 
-#[derive(PartialEq, Debug, Clone)]
+#[macro_use]
+mod macros;
+
+mod borrowed;
+#[cfg(feature = "serde")]
+pub mod de;
+mod encoding;
+mod error;
+pub mod map;
+pub mod ndjson;
+mod number;
+pub mod patch;
+mod pointer;
+pub mod pull;
+pub mod push;
+#[cfg(feature = "serde")]
+pub mod ser;
+mod span;
+pub mod stream;
+mod value;
+mod write;
+
+use crate::error::{RawError, RawKind};
+
+pub use crate::borrowed::BorrowedValue;
+pub use crate::encoding::Encoding;
+pub use crate::error::{Error, ParseError, ParseErrorKind, Position};
+pub use crate::map::Map;
+pub use crate::number::{Number, ParseNumberError};
+pub use crate::pointer::{PointerError, PointerErrorKind};
+pub use crate::span::{NodeSpan, Span, Spans};
+pub use crate::write::{to_string, to_string_pretty, to_writer, to_writer_pretty, WriteOptions};
+
+pub(crate) type PResult<'a, O> = IResult<&'a str, O, RawError<'a>>;
+
+#[derive(PartialEq, Debug, Clone, Default)]
 pub enum Value {
+    #[default]
     Null,
     Boolean(bool),
-    Number(f32),
+    Number(Number),
     String(String),
-    Object(HashMap<String, Value>),
+    Object(Map<String, Value>),
     Array(Vec<Value>),
 }
 
-fn null(i: &str) -> IResult<&str, Value> {
-    map(tag("null"), |_| Value::Null)(i)
+/// Options controlling how JSON text is parsed.
+///
+/// [`from_str`] and friends use the defaults, which are a bit more lenient than RFC 8259.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Reject everything RFC 8259 doesn't allow, e.g. `+1`, `.5` or raw control characters
+    /// inside strings.
+    pub strict: bool,
+    /// What to do with a `\uXXXX` escape for half of a UTF-16 surrogate pair that isn't
+    /// part of a complete pair.
+    pub lone_surrogates: LoneSurrogates,
+    /// What to do when an object has the same key more than once.
+    pub duplicate_keys: DuplicateKeys,
+    /// How deeply arrays and objects may be nested, deeper input fails with
+    /// [`ParseErrorKind::TooDeep`]. `None` removes the limit, which is only safe with
+    /// [`iterative`](ParseOptions::iterative) parsing, and values deeper than the stack
+    /// allows have to be dropped with [`Value::drop_iteratively`].
+    pub max_depth: Option<usize>,
+    /// Parse with an explicit stack instead of recursion, so deeply nested input can't
+    /// overflow the native stack. It is a bit slower than the default parser.
+    pub iterative: bool,
+    /// Longest input accepted, in bytes.
+    pub max_input_len: Option<usize>,
+    /// Longest string or key accepted, in bytes after unescaping.
+    pub max_string_len: Option<usize>,
+    /// Most digits a number may have, counting those of the fraction and exponent.
+    pub max_number_digits: Option<usize>,
+    /// Most elements an array or members an object may have.
+    pub max_items: Option<usize>,
+    /// Most values a document may have, counting arrays and objects as well as
+    /// everything in them.
+    pub max_nodes: Option<usize>,
+    /// What to do with a byte order mark at the start of the input.
+    pub bom: Bom,
+    /// Accept UTF-16 and UTF-32 as well as UTF-8 in
+    /// [`parse_slice`](ParseOptions::parse_slice), telling them apart with
+    /// [`Encoding::detect`]. Positions in errors are counted in the input converted to
+    /// UTF-8.
+    pub detect_encoding: bool,
+    /// Treat `// line` and `/* block */` comments as whitespace.
+    pub allow_comments: bool,
+    /// Accept a comma after the last element of an array or member of an object.
+    pub allow_trailing_commas: bool,
+    /// Accept the values and keys of [JSON5](https://spec.json5.org): identifiers as keys,
+    /// single-quoted strings with the escapes and line continuations of ECMAScript,
+    /// hexadecimal numbers, `Infinity`, `NaN` and more kinds of whitespace. Numbers may
+    /// also have a `+` sign and leading or trailing decimal points, like without
+    /// [`strict`](ParseOptions::strict).
+    ///
+    /// Comments and trailing commas have options of their own, [`ParseOptions::json5`]
+    /// turns on all three.
+    pub json5: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            strict: false,
+            lone_surrogates: LoneSurrogates::default(),
+            duplicate_keys: DuplicateKeys::default(),
+            max_depth: Some(ParseOptions::DEFAULT_MAX_DEPTH),
+            iterative: false,
+            max_input_len: None,
+            max_string_len: None,
+            max_number_digits: None,
+            max_items: None,
+            max_nodes: None,
+            bom: Bom::default(),
+            detect_encoding: false,
+            allow_comments: false,
+            allow_trailing_commas: false,
+            json5: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Nesting limit of the default options, deep enough for any sensible document.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    pub fn strict() -> Self {
+        ParseOptions {
+            strict: true,
+            ..Default::default()
+        }
+    }
+
+    /// JSON with comments, as in the settings files of VS Code and similar tools: both
+    /// kinds of comments and trailing commas are allowed.
+    ///
+    /// ```
+    /// use json_rs_prac::ParseOptions;
+    ///
+    /// let text = "{\n  // Indent with spaces\n  \"tabs\": false, /* for now */\n}";
+    /// assert!(ParseOptions::jsonc().parse_str(text).is_ok());
+    /// assert!(ParseOptions::default().parse_str(text).is_err());
+    /// ```
+    pub fn jsonc() -> Self {
+        ParseOptions {
+            allow_comments: true,
+            allow_trailing_commas: true,
+            ..Default::default()
+        }
+    }
+
+    /// The whole of JSON5, including comments and trailing commas.
+    ///
+    /// ```
+    /// use json_rs_prac::ParseOptions;
+    ///
+    /// let text = "{unquoted: 'and you can quote me on that', hex: 0xdecaf, plus: +1,}";
+    /// let value = ParseOptions::json5().parse_str(text).unwrap();
+    /// assert_eq!(
+    ///     value,
+    ///     json_rs_prac::from_str(
+    ///         r#"{"unquoted": "and you can quote me on that", "hex": 912559, "plus": 1}"#
+    ///     )
+    ///     .unwrap()
+    /// );
+    /// ```
+    pub fn json5() -> Self {
+        ParseOptions {
+            json5: true,
+            ..ParseOptions::jsonc()
+        }
+    }
+}
+
+/// Policy for objects that repeat a key, like `{"a": 1, "a": 2}`.
+///
+/// RFC 8259 leaves this up to the implementation, which is a problem when two parsers
+/// in a pipeline disagree about which value counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Fail with [`ParseErrorKind::DuplicateKey`], which has the positions of both keys.
+    Error,
+    /// Keep the first value, ignoring later ones.
+    FirstWins,
+    /// Keep the last value at the position of the first key.
+    #[default]
+    LastWins,
+    /// Keep every member; see [`Map::get_all`].
+    KeepAll,
+}
+
+/// Policy for a byte order mark (U+FEFF) at the start of the input, which RFC 8259
+/// allows parsers to ignore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bom {
+    #[default]
+    Skip,
+    /// Fail like for any other character that can't start a value.
+    Reject,
 }
 
-fn boolean(i: &str) -> IResult<&str, Value> {
+/// Policy for escapes like `"\ud83d"` that name half of a surrogate pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoneSurrogates {
+    /// Fail to parse the string.
+    #[default]
+    Reject,
+    /// Replace each lone surrogate with U+FFFD.
+    Replace,
+    /// Keep lone surrogates by decoding strings to WTF-8, which encodes them like any
+    /// other code point. Only bytes can hold that: [`wtf8_string`] and byte buffers
+    /// deserialized with `serde`. Strings that have to be valid UTF-8, like those of
+    /// [`Value`], still fail as with `Reject`.
+    Keep,
+}
+
+impl LoneSurrogates {
+    /// Whether a string decoded to WTF-8 if `wtf8` is set has to fail on them.
+    fn rejects(self, wtf8: bool) -> bool {
+        match self {
+            LoneSurrogates::Reject => true,
+            LoneSurrogates::Replace => false,
+            LoneSurrogates::Keep => !wtf8,
+        }
+    }
+}
+
+/// A tree of JSON values the grammar can build, so the same parsers produce both
+/// [`Value`] and [`BorrowedValue`].
+pub(crate) trait Tree<'a>: Sized {
+    type Key: Hash + Eq + Clone + AsRef<str>;
+
+    fn null() -> Self;
+    fn boolean(b: bool) -> Self;
+    fn number(n: Number) -> Self;
+    fn string(s: Cow<'a, str>) -> Self;
+    fn key(s: Cow<'a, str>) -> Self::Key;
+    fn array(items: Vec<Self>) -> Self;
+    fn object(map: Map<Self::Key, Self>) -> Self;
+    /// The values in an array or object, nothing for any other value.
+    fn into_items(self) -> Vec<Self>;
+}
+
+/// Drops `values` one array or object at a time. Dropping them the usual way recurses
+/// once per level of nesting, which values built with [`ParseOptions::iterative`] and
+/// no depth limit can have too many of for the stack.
+pub(crate) fn drop_iteratively<'a, V: Tree<'a>>(values: impl IntoIterator<Item = V>) {
+    let mut stack: Vec<V> = values.into_iter().collect();
+    while let Some(value) = stack.pop() {
+        stack.extend(value.into_items());
+    }
+}
+
+impl<'a> Tree<'a> for Value {
+    type Key = String;
+
+    fn null() -> Self {
+        Value::Null
+    }
+
+    fn boolean(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+
+    fn number(n: Number) -> Self {
+        Value::Number(n)
+    }
+
+    fn string(s: Cow<'a, str>) -> Self {
+        Value::String(s.into_owned())
+    }
+
+    fn key(s: Cow<'a, str>) -> String {
+        s.into_owned()
+    }
+
+    fn array(items: Vec<Self>) -> Self {
+        Value::Array(items)
+    }
+
+    fn object(map: Map<String, Self>) -> Self {
+        Value::Object(map)
+    }
+
+    fn into_items(self) -> Vec<Self> {
+        match self {
+            Value::Array(arr) => arr,
+            Value::Object(obj) => obj.into_iter().map(|(_, v)| v).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn null<'a, V: Tree<'a>>(i: &'a str) -> PResult<'a, V> {
+    map(tag("null"), |_| V::null())(i)
+}
+
+fn boolean<'a, V: Tree<'a>>(i: &'a str) -> PResult<'a, V> {
     alt((
-        map(tag("true"), |_| Value::Boolean(true)),
-        map(tag("false"), |_| Value::Boolean(false)),
+        map(tag("true"), |_| V::boolean(true)),
+        map(tag("false"), |_| V::boolean(false)),
     ))(i)
 }
 
-fn number(i: &str) -> IResult<&str, Value> {
-    map(float, Value::Number)(i)
+/// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+fn json_number(i: &str) -> PResult<'_, &str> {
+    recognize(tuple((
+        opt(char('-')),
+        alt((tag("0"), recognize(pair(one_of("123456789"), digit0)))),
+        opt(pair(char('.'), digit1)),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(i)
+}
+
+/// A decimal number as [`recognize_float`] takes it, or one of the numbers JSON5 adds:
+/// hexadecimal integers, `Infinity` and `NaN`, all with an optional sign.
+fn json5_number(i: &str) -> PResult<'_, &str> {
+    alt((
+        recognize(pair(
+            opt(one_of("+-")),
+            alt((
+                tag("Infinity"),
+                tag("NaN"),
+                recognize(pair(alt((tag("0x"), tag("0X"))), hex_digit1)),
+            )),
+        )),
+        recognize_float,
+    ))(i)
+}
+
+pub(crate) fn js_number<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, Number> {
+    let text: fn(&'a str) -> PResult<'a, &'a str> = if opts.json5 {
+        json5_number
+    } else if opts.strict {
+        json_number
+    } else {
+        recognize_float
+    };
+    let (rest, literal) = text(i)?;
+    if let Some(max_number_digits) = opts.max_number_digits {
+        let unsigned = literal.trim_start_matches(&['+', '-'][..]);
+        // The letters of JSON5 hexadecimal numbers are digits too
+        let digits = match unsigned.strip_prefix("0x").or(unsigned.strip_prefix("0X")) {
+            Some(hex) => hex.bytes().filter(u8::is_ascii_hexdigit).count(),
+            None => literal.bytes().filter(u8::is_ascii_digit).count(),
+        };
+        if digits > max_number_digits {
+            return Err(nom::Err::Failure(RawError::new(
+                i,
+                RawKind::NumberTooLong { max_number_digits },
+            )));
+        }
+    }
+    let n = if opts.json5 {
+        Number::from_json5(literal)
+    } else {
+        literal.parse()
+    };
+    // The literal is well-formed, so it can only be too large for `f64`
+    let n = n.map_err(|_| nom::Err::Failure(RawError::new(i, RawKind::NumberOutOfRange)))?;
+    Ok((rest, n))
+}
+
+fn number<'a, V: Tree<'a>>(i: &'a str, opts: &ParseOptions) -> PResult<'a, V> {
+    map(|i| js_number(i, opts), V::number)(i)
 }
 
 fn unescape(c: char) -> char {
     match c {
+        'b' => '\u{0008}',
+        'f' => '\u{000c}',
         'n' => '\n',
         'r' => '\r',
         't' => '\t',
@@ -45,105 +392,794 @@ fn unescape(c: char) -> char {
     }
 }
 
-fn simple_escape_char(i: &str) -> IResult<&str, char> {
-    map(one_of("\"\\nrt"), unescape)(i)
+/// A decoded piece of a string literal.
+#[derive(Clone, Copy)]
+enum Unit {
+    Char(char),
+    /// A `\uXXXX` escape naming half of a surrogate pair without the other half.
+    LoneSurrogate(u32),
+    /// A backslash before a line break, which JSON5 drops from the string.
+    LineContinuation,
 }
 
-fn hex(i: &str) -> IResult<&str, u32> {
+fn simple_escape_char(i: &str) -> PResult<'_, Unit> {
+    map(one_of("\"\\/bfnrt"), |c| Unit::Char(unescape(c)))(i)
+}
+
+fn hex(i: &str) -> PResult<'_, u32> {
     map(take_while_m_n(4, 4, char::is_hex_digit), |hex| {
         u32::from_str_radix(hex, 16).unwrap()
     })(i)
 }
 
-fn hex_escape_char(i: &str) -> IResult<&str, char> {
-    preceded(char('u'), map(hex, |hex| hex.try_into().unwrap()))(i)
+fn is_high_surrogate(u: u32) -> bool {
+    (0xd800..0xdc00).contains(&u)
 }
 
-fn escape_char(i: &str) -> IResult<&str, char> {
-    preceded(char('\\'), alt((hex_escape_char, simple_escape_char)))(i)
+fn is_low_surrogate(u: u32) -> bool {
+    (0xdc00..0xe000).contains(&u)
 }
 
-fn normal_char(i: &str) -> IResult<&str, char> {
-    none_of("\\\"")(i)
+fn hex_escape_char<'a>(i: &'a str, opts: &ParseOptions, wtf8: bool) -> PResult<'a, Unit> {
+    let start = i;
+    let reject = opts.lone_surrogates.rejects(wtf8);
+    let (i, hi) = preceded(char('u'), hex)(i)?;
+
+    if is_high_surrogate(hi) {
+        let (i, lo) = opt(preceded(
+            tag("\\u"),
+            verify(hex, |&lo| is_low_surrogate(lo)),
+        ))(i)?;
+        let unit = match lo {
+            Some(lo) => {
+                let c = 0x10000 + ((hi - 0xd800) << 10) + (lo - 0xdc00);
+                Unit::Char(c.try_into().unwrap())
+            }
+            None if reject => {
+                return Err(nom::Err::Failure(RawError::expected(
+                    i,
+                    "low surrogate escape",
+                )));
+            }
+            None => Unit::LoneSurrogate(hi),
+        };
+        Ok((i, unit))
+    } else if is_low_surrogate(hi) {
+        if reject {
+            return Err(nom::Err::Failure(RawError::expected(
+                start,
+                "high surrogate escape before a low surrogate",
+            )));
+        }
+        Ok((i, Unit::LoneSurrogate(hi)))
+    } else {
+        Ok((i, Unit::Char(hi.try_into().unwrap())))
+    }
 }
 
-fn js_string(i: &str) -> IResult<&str, String> {
-    map(
-        delimited(char('"'), many0(alt((escape_char, normal_char))), char('"')),
-        |chars| chars.into_iter().collect(),
+/// The escapes of ECMAScript that JSON lacks: `\v`, `\0`, `\xHH`, line continuations
+/// and any other character standing for itself.
+fn json5_escape_char(i: &str) -> PResult<'_, Unit> {
+    alt((
+        map(
+            alt((
+                tag("\r\n"),
+                tag("\n"),
+                tag("\r"),
+                tag("\u{2028}"),
+                tag("\u{2029}"),
+            )),
+            |_| Unit::LineContinuation,
+        ),
+        map(char('v'), |_| Unit::Char('\u{000b}')),
+        map(terminated(char('0'), not(digit1)), |_| Unit::Char('\0')),
+        map(
+            preceded(char('x'), take_while_m_n(2, 2, |c: char| c.is_hex_digit())),
+            |hex| Unit::Char(char::from(u8::from_str_radix(hex, 16).unwrap())),
+        ),
+        map(
+            verify(anychar, |&c| !c.is_ascii_digit() && c != 'x' && c != 'u'),
+            Unit::Char,
+        ),
+    ))(i)
+}
+
+fn escape_char<'a>(i: &'a str, opts: &ParseOptions, wtf8: bool) -> PResult<'a, Unit> {
+    preceded(
+        char('\\'),
+        cut(expect("escape sequence", |i| {
+            if opts.json5 {
+                alt((
+                    |i| hex_escape_char(i, opts, wtf8),
+                    simple_escape_char,
+                    json5_escape_char,
+                ))(i)
+            } else {
+                alt((|i| hex_escape_char(i, opts, wtf8), simple_escape_char))(i)
+            }
+        })),
     )(i)
 }
 
-fn string(i: &str) -> IResult<&str, Value> {
-    map(js_string, Value::String)(i)
+/// Whether `c` may appear unescaped in a string literal quoted with `quote`.
+fn is_normal_char(c: char, quote: char, opts: &ParseOptions) -> bool {
+    c != quote
+        && c != '\\'
+        && !(opts.strict && c < '\u{0020}')
+        && !(opts.json5 && (c == '\n' || c == '\r'))
 }
 
-fn array(i: &str) -> IResult<&str, Value> {
+fn normal_char<'a>(i: &'a str, quote: char, opts: &ParseOptions) -> PResult<'a, Unit> {
     map(
-        delimited(char('['), separated_list(char(','), value), char(']')),
-        Value::Array,
+        verify(anychar, |&c| is_normal_char(c, quote, opts)),
+        Unit::Char,
     )(i)
 }
 
-fn js_spaces<I: Clone + InputTakeAtPosition, E: ParseError<I>>(i: I) -> IResult<I, I, E>
-where
-    I::Item: Clone + AsChar,
-{
-    take_while(|c: I::Item| match c.as_char() {
-        ' ' | '\n' | '\r' | '\u{0009}' => true,
-        _ => false,
+/// An opening quote: `"`, or in JSON5 also `'`.
+fn open_quote<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, char> {
+    if opts.json5 {
+        one_of("\"'")(i)
+    } else {
+        char('"')(i)
+    }
+}
+
+/// The pieces of a string literal, lone surrogates being dealt with as for a string
+/// decoded to WTF-8 if `wtf8` is set.
+fn string_units<'a>(i: &'a str, opts: &ParseOptions, wtf8: bool) -> PResult<'a, Vec<Unit>> {
+    let (i, quote) = open_quote(i, opts)?;
+    let (i, units) = many0(alt((
+        |i| escape_char(i, opts, wtf8),
+        |i| normal_char(i, quote, opts),
+    )))(i)?;
+    let label = if quote == '"' {
+        "closing '\"'"
+    } else {
+        "closing \"'\""
+    };
+    let (i, _) = expect(label, char(quote))(i)?;
+    Ok((i, units))
+}
+
+/// Fails if the string starting at `i` is `len` bytes long and that is too long.
+fn check_string_len<'a>(i: &'a str, len: usize, opts: &ParseOptions) -> PResult<'a, ()> {
+    match opts.max_string_len {
+        Some(max_string_len) if len > max_string_len => Err(nom::Err::Failure(RawError::new(
+            i,
+            RawKind::StringTooLong { max_string_len },
+        ))),
+        _ => Ok((i, ())),
+    }
+}
+
+pub(crate) fn js_string<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, String> {
+    let (rest, s) = map(
+        |i| string_units(i, opts, false),
+        |units| {
+            units
+                .into_iter()
+                .filter_map(|unit| match unit {
+                    Unit::Char(c) => Some(c),
+                    // Only reachable with `LoneSurrogates::Replace`, the others already failed
+                    Unit::LoneSurrogate(_) => Some(std::char::REPLACEMENT_CHARACTER),
+                    Unit::LineContinuation => None,
+                })
+                .collect::<String>()
+        },
+    )(i)?;
+    check_string_len(i, s.len(), opts)?;
+    Ok((rest, s))
+}
+
+/// Parses `s`, a single string literal, into WTF-8.
+///
+/// With [`LoneSurrogates::Keep`] this keeps lone surrogates, which `String` can't hold,
+/// so it is the only way to get at them losslessly. Any other policy applies as for
+/// strings, making the result valid UTF-8.
+///
+/// ```
+/// use json_rs_prac::{wtf8_string, LoneSurrogates, ParseOptions};
+///
+/// let opts = ParseOptions {
+///     lone_surrogates: LoneSurrogates::Keep,
+///     ..Default::default()
+/// };
+/// assert_eq!(wtf8_string(r#""\ud83d!""#, &opts).unwrap(), b"\xed\xa0\xbd!");
+/// assert!(wtf8_string(r#""\ud83d!""#, &ParseOptions::default()).is_err());
+/// ```
+pub fn wtf8_string(s: &str, opts: &ParseOptions) -> Result<Vec<u8>, ParseError> {
+    check_input_len(s, 0, opts).map_err(|e| ParseError::new(s, e))?;
+    let (_, bytes) = finish(s, |i| {
+        all_consuming(delimited(
+            |i| js_spaces_and_comments(i, opts),
+            |i| js_wtf8_string(i, opts),
+            |i| js_spaces_and_comments(i, opts),
+        ))(opts.skip_bom(i))
+    })?;
+    Ok(bytes)
+}
+
+pub(crate) fn js_wtf8_string<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, Vec<u8>> {
+    let (rest, buf) = map(
+        |i| string_units(i, opts, true),
+        |units| {
+            let mut buf = Vec::with_capacity(units.len());
+            for unit in units {
+                match unit {
+                    Unit::Char(c) => buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                    Unit::LoneSurrogate(_) if opts.lone_surrogates == LoneSurrogates::Replace => {
+                        buf.extend_from_slice("\u{fffd}".as_bytes())
+                    }
+                    Unit::LoneSurrogate(u) => buf.extend_from_slice(&[
+                        0xe0 | (u >> 12) as u8,
+                        0x80 | ((u >> 6) & 0x3f) as u8,
+                        0x80 | (u & 0x3f) as u8,
+                    ]),
+                    Unit::LineContinuation => {}
+                }
+            }
+            buf
+        },
+    )(i)?;
+    check_string_len(i, buf.len(), opts)?;
+    Ok((rest, buf))
+}
+
+/// Parses a string literal, borrowing it from the input unless it has escapes.
+pub(crate) fn js_cow_string<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, Cow<'a, str>> {
+    let (rest, quote) = open_quote(i, opts)?;
+    let plain = rest
+        .find(|c| !is_normal_char(c, quote, opts))
+        .unwrap_or(rest.len());
+
+    if rest[plain..].starts_with(quote) {
+        check_string_len(i, plain, opts)?;
+        Ok((&rest[plain + 1..], Cow::Borrowed(&rest[..plain])))
+    } else {
+        // Escapes, or an error to report
+        map(|i| js_string(i, opts), Cow::Owned)(i)
+    }
+}
+
+fn string<'a, V: Tree<'a>>(i: &'a str, opts: &ParseOptions) -> PResult<'a, V> {
+    map(|i| js_cow_string(i, opts), V::string)(i)
+}
+
+/// Whether `c` may start an identifier. Letters are those of [`char::is_alphabetic`],
+/// which is close to what ECMAScript counts.
+pub(crate) fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
+}
+
+pub(crate) fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'
+}
+
+/// An ECMAScript identifier name, which JSON5 allows as a key. It may have `\uXXXX`
+/// escapes, as long as they stand for characters an identifier may have there.
+fn json5_identifier<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, Cow<'a, str>> {
+    let mut name = String::new();
+    let mut escaped = false;
+    let mut rest = i;
+
+    loop {
+        let (after, c) = match rest.strip_prefix('\\') {
+            Some(escape) => cut(expect(
+                "unicode escape",
+                preceded(char('u'), map_opt(hex, std::char::from_u32)),
+            ))(escape)?,
+            None => match rest.chars().next() {
+                Some(c) => (&rest[c.len_utf8()..], c),
+                None => break,
+            },
+        };
+        let valid = if name.is_empty() {
+            is_identifier_start(c)
+        } else {
+            is_identifier_part(c)
+        };
+        if !valid && rest.starts_with('\\') {
+            return Err(nom::Err::Failure(RawError::expected(
+                rest,
+                "identifier character",
+            )));
+        } else if !valid {
+            break;
+        }
+        escaped |= rest.starts_with('\\');
+        name.push(c);
+        rest = after;
+    }
+
+    if name.is_empty() {
+        return Err(nom::Err::Error(RawError::expected(i, "identifier")));
+    }
+    check_string_len(i, name.len(), opts)?;
+    if escaped {
+        Ok((rest, Cow::Owned(name)))
+    } else {
+        Ok((rest, Cow::Borrowed(&i[..i.len() - rest.len()])))
+    }
+}
+
+/// What an object key is called in errors.
+pub(crate) fn key_label(opts: &ParseOptions) -> &'static str {
+    if opts.json5 {
+        "key"
+    } else {
+        "string"
+    }
+}
+
+/// Parses an object key: a string, or in JSON5 also an identifier.
+pub(crate) fn js_key<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, Cow<'a, str>> {
+    expect(key_label(opts), |i| {
+        if opts.json5 && !i.starts_with(&['"', '\''][..]) {
+            json5_identifier(i, opts)
+        } else {
+            js_cow_string(i, opts)
+        }
     })(i)
 }
 
-#[inline]
-fn ws<I: Clone + InputTakeAtPosition, O, E: ParseError<I>>(
-    f: impl Fn(I) -> IResult<I, O, E>,
-) -> impl Fn(I) -> IResult<I, O, E>
+/// Parses `open item (, item)* close` where the items may be surrounded by whitespace,
+/// with a comma after the last item if [`ParseOptions::allow_trailing_commas`] is set.
+fn list<'a, O>(
+    i: &'a str,
+    open: char,
+    item: impl Fn(&'a str) -> PResult<'a, O>,
+    close: char,
+    opts: &ParseOptions,
+) -> PResult<'a, Vec<O>> {
+    let (mut i, _) = ws(char(open), opts)(i)?;
+    let mut items = Vec::new();
+    // Whether `close` may come instead of the next item
+    let mut may_close = true;
+
+    loop {
+        let (rest, next) = if may_close {
+            // `alt((char(close), item))`, spelled out to keep nesting from using up the stack
+            let e = match char::<_, RawError>(close)(i) {
+                Ok((rest, _)) => return Ok((rest, items)),
+                Err(nom::Err::Error(e)) => e,
+                Err(e) => return Err(e),
+            };
+            check_items(i, items.len(), opts.max_items)?;
+            item(i).map_err(|err| match err {
+                nom::Err::Error(other) => nom::Err::Error(nom::error::ParseError::or(e, other)),
+                err => err,
+            })?
+        } else {
+            check_items(i, items.len(), opts.max_items)?;
+            item(i)?
+        };
+        items.push(next);
+
+        let (rest, more) = alt((
+            map(ws(char(','), opts), |_| true),
+            map(char(close), |_| false),
+        ))(rest)?;
+        if !more {
+            return Ok((rest, items));
+        }
+        i = rest;
+        may_close = opts.allow_trailing_commas;
+    }
+}
+
+/// Fails if the item starting at `i` would be one too many after `len` others.
+fn check_items(i: &str, len: usize, max_items: Option<usize>) -> PResult<'_, ()> {
+    match max_items {
+        Some(max_items) if len >= max_items => Err(nom::Err::Failure(RawError::new(
+            i,
+            RawKind::TooManyItems { max_items },
+        ))),
+        _ => Ok((i, ())),
+    }
+}
+
+/// Fails if a value starting at `i` would be one too many for the document.
+fn count_node<'a>(i: &'a str, opts: &ParseOptions, nodes: &Cell<usize>) -> PResult<'a, ()> {
+    nodes.set(nodes.get() + 1);
+    match opts.max_nodes {
+        Some(max_nodes) if nodes.get() > max_nodes => Err(nom::Err::Failure(RawError::new(
+            i,
+            RawKind::TooManyNodes { max_nodes },
+        ))),
+        _ => Ok((i, ())),
+    }
+}
+
+/// Fails if an array or object starting at `i` would be nested deeper than allowed.
+fn check_depth<'a>(i: &'a str, opts: &ParseOptions, depth: usize) -> PResult<'a, ()> {
+    match opts.max_depth {
+        Some(max_depth) if depth > max_depth => Err(nom::Err::Failure(RawError::new(
+            i,
+            RawKind::TooDeep { max_depth },
+        ))),
+        _ => Ok((i, ())),
+    }
+}
+
+fn array<'a, V: Tree<'a>>(
+    i: &'a str,
+    opts: &ParseOptions,
+    depth: usize,
+    nodes: &Cell<usize>,
+) -> PResult<'a, V> {
+    check_depth(i, opts, depth)?;
+    let (rest, items) =
+        list(i, '[', |i| element(i, opts, depth, nodes), ']', opts).map_err(|e| {
+            e.map(|mut e| {
+                e.context.push("array");
+                e
+            })
+        })?;
+    Ok((rest, V::array(items)))
+}
+
+pub(crate) fn js_spaces<I: Clone + InputTakeAtPosition, E: nom::error::ParseError<I>>(
+    i: I,
+) -> IResult<I, I, E>
 where
     I::Item: Clone + AsChar,
 {
-    terminated(f, js_spaces)
+    take_while(|c: I::Item| matches!(c.as_char(), ' ' | '\n' | '\r' | '\u{0009}'))(i)
 }
 
-fn object(i: &str) -> IResult<&str, Value> {
-    context(
-        "object",
-        map(
-            delimited(
-                ws(char('{')),
-                separated_list(
-                    ws(char(',')),
-                    context(
-                        "object item",
-                        separated_pair(ws(js_string), ws(char(':')), value),
-                    ),
-                ),
-                char('}'),
-            ),
-            |kv| Value::Object(kv.into_iter().collect()),
-        ),
-    )(i)
+/// Whitespace as JSON5 has it, which is that of ECMAScript: Unicode space separators,
+/// vertical tab, form feed, byte order mark and the line and paragraph separators.
+fn is_json5_space(c: char) -> bool {
+    matches!(
+        c,
+        '\t' | '\n' | '\u{000b}' | '\u{000c}' | '\r' | ' ' | '\u{00a0}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200a}'
+                | '\u{2028}'
+                | '\u{2029}'
+                | '\u{202f}'
+                | '\u{205f}'
+                | '\u{3000}'
+                | '\u{feff}'
+    )
+}
+
+/// Skips whitespace, which JSON5 has more kinds of.
+pub(crate) fn skip_spaces<'a>(i: &'a str, opts: &ParseOptions) -> &'a str {
+    if opts.json5 {
+        i.trim_start_matches(is_json5_space)
+    } else {
+        js_spaces::<_, RawError>(i).unwrap().0
+    }
+}
+
+/// A comment at the start of `s`: its length, and whether it is closed. A line comment
+/// running to the end of `s` counts as not closed, more of it may follow.
+pub(crate) fn js_comment(s: &str) -> Option<(usize, bool)> {
+    js_comment_from(s, 0)
+}
+
+/// Like [`js_comment`], but the end is only looked for from byte `from` on, as a
+/// comment found to be cut off before only needs the text after that searched.
+pub(crate) fn js_comment_from(s: &str, from: usize) -> Option<(usize, bool)> {
+    let end: &[u8] = match s.get(..2) {
+        Some("//") => b"\n",
+        Some("/*") => b"*/",
+        _ => return None,
+    };
+    // The end may have started right before `from`
+    let start = cmp::max(2, (from + 1).saturating_sub(end.len()));
+    Some(
+        match s.as_bytes()[start..]
+            .windows(end.len())
+            .position(|w| w == end)
+        {
+            Some(at) => (start + at + end.len(), true),
+            None => (s.len(), false),
+        },
+    )
+}
+
+/// Skips whitespace, and comments if [`ParseOptions::allow_comments`] is set.
+pub(crate) fn js_spaces_and_comments<'a>(i: &'a str, opts: &ParseOptions) -> PResult<'a, &'a str> {
+    let mut rest = skip_spaces(i, opts);
+    if opts.allow_comments {
+        while let Some((len, closed)) = js_comment(rest) {
+            if !closed && rest.starts_with("/*") {
+                return Err(nom::Err::Failure(RawError::expected(&rest[len..], "'*/'")));
+            }
+            rest = skip_spaces(&rest[len..], opts);
+        }
+    }
+    Ok((rest, &i[..i.len() - rest.len()]))
+}
+
+fn ws<'a, 'o, O>(
+    f: impl Fn(&'a str) -> PResult<'a, O> + 'o,
+    opts: &'o ParseOptions,
+) -> impl Fn(&'a str) -> PResult<'a, O> + 'o {
+    move |i| {
+        let (i, o) = f(i)?;
+        let (i, _) = js_spaces_and_comments(i, opts)?;
+        Ok((i, o))
+    }
+}
+
+/// Reports `label` as the expected input if `f` fails without consuming anything.
+pub(crate) fn expect<'a, O>(
+    label: &'static str,
+    f: impl Fn(&'a str) -> PResult<'a, O>,
+) -> impl Fn(&'a str) -> PResult<'a, O> {
+    move |i| match f(i) {
+        Err(nom::Err::Error(e)) if e.input.len() == i.len() => {
+            Err(nom::Err::Error(RawError::expected(i, label)))
+        }
+        res => res,
+    }
+}
+
+/// Parses `"key": value`, also returning where the key starts.
+fn member<'a, V: Tree<'a>>(
+    i: &'a str,
+    opts: &ParseOptions,
+    depth: usize,
+    nodes: &Cell<usize>,
+) -> PResult<'a, (&'a str, V::Key, V)> {
+    let (rest, key) = ws(map(|i| js_key(i, opts), V::key), opts)(i)?;
+    let (rest, _) = ws(char(':'), opts)(rest)?;
+    let (rest, value) = element(rest, opts, depth, nodes)?;
+    Ok((rest, (i, key, value)))
+}
+
+/// `context("object item", member)`, without the extra stack frames.
+fn member_item<'a, V: Tree<'a>>(
+    i: &'a str,
+    opts: &ParseOptions,
+    depth: usize,
+    nodes: &Cell<usize>,
+) -> PResult<'a, (&'a str, V::Key, V)> {
+    member(i, opts, depth, nodes).map_err(|e| {
+        e.map(|mut e| {
+            e.context.push("object item");
+            e
+        })
+    })
 }
 
-fn value_inner(i: &str) -> IResult<&str, Value> {
-    alt((null, boolean, number, string, array, object))(i)
+fn object<'a, V: Tree<'a>>(
+    i: &'a str,
+    opts: &ParseOptions,
+    depth: usize,
+    nodes: &Cell<usize>,
+) -> PResult<'a, V> {
+    check_depth(i, opts, depth)?;
+    let (rest, members) =
+        list(i, '{', |i| member_item(i, opts, depth, nodes), '}', opts).map_err(|e| {
+            e.map(|mut e| {
+                e.context.push("object");
+                e
+            })
+        })?;
+    Ok((rest, collect_members(members, opts)?))
 }
 
-pub fn value(i: &str) -> IResult<&str, Value> {
-    delimited(js_spaces, value_inner, js_spaces)(i)
+/// Builds an object from its members, applying the duplicate key policy.
+///
+/// Kept out of `object` so the stack frame of every level of nesting stays small.
+fn collect_members<'a, V: Tree<'a>>(
+    members: Vec<(&'a str, V::Key, V)>,
+    opts: &ParseOptions,
+) -> Result<V, nom::Err<RawError<'a>>> {
+    let mut map: Map<V::Key, V> = Map::with_capacity(members.len());
+    let mut positions = Vec::new();
+
+    for (pos, key, value) in members {
+        match opts.duplicate_keys {
+            DuplicateKeys::LastWins => {
+                map.insert(key, value);
+            }
+            DuplicateKeys::FirstWins => {
+                if !map.contains_key(&key) {
+                    map.insert(key, value);
+                }
+            }
+            DuplicateKeys::KeepAll => map.append(key, value),
+            DuplicateKeys::Error => {
+                if let Some(idx) = map.get_index_of(&key) {
+                    let first = positions[idx];
+                    let key = key.as_ref().to_string();
+                    let mut e = RawError::new(pos, RawKind::DuplicateKey { key, first });
+                    e.context.push("object");
+                    return Err(nom::Err::Failure(e));
+                }
+                map.insert(key, value);
+                positions.push(pos);
+            }
+        }
+    }
+
+    Ok(V::object(map))
+}
+
+/// Parses a value inside `depth` arrays and objects.
+fn value_inner<'a, V: Tree<'a>>(
+    i: &'a str,
+    opts: &ParseOptions,
+    depth: usize,
+    nodes: &Cell<usize>,
+) -> PResult<'a, V> {
+    // Dispatching on the first character keeps the stack frames per level of nesting small
+    match i.chars().next() {
+        Some('[') => array(i, opts, depth + 1, nodes),
+        Some('{') => object(i, opts, depth + 1, nodes),
+        _ => expect(
+            "value",
+            alt((null, boolean, |i| number(i, opts), |i| string(i, opts))),
+        )(i),
+    }
+}
+
+fn element<'a, V: Tree<'a>>(
+    i: &'a str,
+    opts: &ParseOptions,
+    depth: usize,
+    nodes: &Cell<usize>,
+) -> PResult<'a, V> {
+    let (i, _) = js_spaces_and_comments(i, opts)?;
+    count_node(i, opts, nodes)?;
+    let (i, value) = value_inner(i, opts, depth, nodes)?;
+    let (i, _) = js_spaces_and_comments(i, opts)?;
+    Ok((i, value))
+}
+
+/// Fails if `input`, which starts `offset` bytes into the document, goes on past
+/// [`ParseOptions::max_input_len`].
+pub(crate) fn check_input_len<'a>(
+    input: &'a str,
+    offset: usize,
+    opts: &ParseOptions,
+) -> Result<(), RawError<'a>> {
+    match opts.max_input_len {
+        Some(max_input_len) if offset + input.len() > max_input_len => {
+            let mut at = max_input_len.saturating_sub(offset);
+            while !input.is_char_boundary(at) {
+                at -= 1;
+            }
+            Err(RawError::new(
+                &input[at..],
+                RawKind::InputTooLong { max_input_len },
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Runs `f` on the whole of `input`, translating its error into a [`ParseError`].
+fn finish<'a, O>(
+    input: &'a str,
+    f: impl Fn(&'a str) -> PResult<'a, O>,
+) -> Result<(&'a str, O), ParseError> {
+    f(input).map_err(|e| match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => ParseError::new(input, e),
+        nom::Err::Incomplete(_) => ParseError::new(input, RawError::expected("", "more input")),
+    })
+}
+
+impl ParseOptions {
+    /// Parses `s` as a single JSON value, only whitespace may follow it.
+    pub fn parse_str(&self, s: &str) -> Result<Value, Error> {
+        check_input_len(s, 0, self).map_err(|e| ParseError::new(s, e))?;
+        self.parse_tree(s)
+    }
+
+    /// Like [`parse_str`](ParseOptions::parse_str), but strings without escapes borrow
+    /// from `s` instead of being copied.
+    pub fn parse_borrowed<'a>(&self, s: &'a str) -> Result<BorrowedValue<'a>, Error> {
+        check_input_len(s, 0, self).map_err(|e| ParseError::new(s, e))?;
+        self.parse_tree(s)
+    }
+
+    /// Like [`parse_str`](ParseOptions::parse_str), also returning where each value and
+    /// key sits in `s`. Always parses [`iterative`](ParseOptions::iterative)ly.
+    pub fn parse_spanned(&self, s: &str) -> Result<(Value, Spans), Error> {
+        span::parse(s, self)
+    }
+
+    fn parse_tree<'a, V: Tree<'a>>(&self, s: &'a str) -> Result<V, Error> {
+        if self.iterative {
+            return Ok(pull::build(s, self)?);
+        }
+
+        let nodes = Cell::new(0);
+        let (_, value) = finish(s, |i| {
+            // `all_consuming` reports anything after the value as expecting the end of input
+            all_consuming(|i| element(i, self, 0, &nodes))(self.skip_bom(i))
+        })?;
+        Ok(value)
+    }
+
+    /// Strips a byte order mark from the start of `s` if [`bom`](ParseOptions::bom) says so.
+    pub(crate) fn skip_bom<'a>(&self, s: &'a str) -> &'a str {
+        match self.bom {
+            Bom::Skip => s.strip_prefix('\u{feff}').unwrap_or(s),
+            Bom::Reject => s,
+        }
+    }
+
+    /// Like [`parse_str`](ParseOptions::parse_str), but for bytes. They are expected to
+    /// be UTF-8 unless [`detect_encoding`](ParseOptions::detect_encoding) is set, invalid
+    /// sequences fail with [`ParseErrorKind::InvalidEncoding`].
+    pub fn parse_slice(&self, v: &[u8]) -> Result<Value, Error> {
+        // `decode` already applied `max_input_len` to the bytes
+        self.parse_tree(&encoding::decode(v, self)?)
+    }
+
+    /// Reads `reader` to the end and parses its contents as a single JSON value.
+    ///
+    /// With [`max_input_len`](ParseOptions::max_input_len), reading stops right after
+    /// the limit.
+    pub fn parse_reader(&self, reader: impl Read) -> Result<Value, Error> {
+        let limit = self.max_input_len.map_or(u64::MAX, |max| max as u64 + 1);
+        let mut buf = Vec::new();
+        reader.take(limit).read_to_end(&mut buf)?;
+        self.parse_slice(&buf)
+    }
+}
+
+/// Parses a JSON text.
+///
+/// ```
+/// let value = json_rs_prac::from_str(r#"{ "abc": [1, 2] }"#).unwrap();
+/// assert!(json_rs_prac::from_str("1 2").is_err());
+/// ```
+pub fn from_str(s: &str) -> Result<Value, Error> {
+    ParseOptions::default().parse_str(s)
+}
+
+/// Parses a JSON text without copying strings that have no escapes.
+///
+/// ```
+/// use std::borrow::Cow;
+/// use json_rs_prac::BorrowedValue;
+///
+/// let value = json_rs_prac::from_str_borrowed(r#"["plain", "esc\"aped"]"#).unwrap();
+/// match &value {
+///     BorrowedValue::Array(items) => {
+///         assert!(matches!(items[0], BorrowedValue::String(Cow::Borrowed("plain"))));
+///         assert!(matches!(items[1], BorrowedValue::String(Cow::Owned(_))));
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn from_str_borrowed(s: &str) -> Result<BorrowedValue<'_>, Error> {
+    ParseOptions::default().parse_borrowed(s)
+}
+
+/// Parses a JSON text, noting where each value and key is for diagnostics, see [`Spans`].
+pub fn from_str_spanned(s: &str) -> Result<(Value, Spans), Error> {
+    ParseOptions::default().parse_spanned(s)
+}
+
+/// Parses a JSON text from UTF-8 encoded bytes.
+pub fn from_slice(v: &[u8]) -> Result<Value, Error> {
+    ParseOptions::default().parse_slice(v)
+}
+
+/// Parses a JSON text read from `reader`.
+pub fn from_reader(reader: impl Read) -> Result<Value, Error> {
+    ParseOptions::default().parse_reader(reader)
 }
 
 #[test]
 fn string_test() {
-    let (left, value) = string("\"abd\\tbc\"foo").unwrap();
+    let (left, value) = string::<Value>("\"abd\\tbc\"foo", &ParseOptions::default()).unwrap();
     assert_eq!(left, "foo");
     assert_eq!(value, Value::String("abd\tbc".into()));
 }
 
 #[test]
 fn value_test() {
-    let (left, value) = value(r#" { "abc" : "def", "foo": ["bar", 123] } "#).unwrap();
-    assert!(left.is_empty());
+    let value = from_str(r#" { "abc" : "def", "foo": ["bar", 123] } "#).unwrap();
     assert_eq!(
         value,
         Value::Object(
@@ -151,7 +1187,7 @@ fn value_test() {
                 ("abc".into(), Value::String("def".into())),
                 (
                     "foo".into(),
-                    Value::Array(vec![Value::String("bar".into()), Value::Number(123.0),])
+                    Value::Array(vec![Value::String("bar".into()), Value::Number(123.into()),])
                 )
             ]
             .iter()
@@ -163,7 +1199,7 @@ fn value_test() {
 
 #[test]
 fn new_line_value_test() {
-    let (_, value) = value(
+    let value = from_str(
         "
     {
     \"glossary\": 123
@@ -176,10 +1212,572 @@ fn new_line_value_test() {
     assert_eq!(
         value,
         Value::Object(
-            [("glossary".into(), Value::Number(123.0))]
+            [("glossary".into(), Value::Number(123.into()))]
                 .iter()
                 .cloned()
                 .collect()
         )
     );
 }
+
+#[test]
+fn number_test() {
+    let value = from_str("[16777217, -9007199254740993, 0.5]").unwrap();
+    assert_eq!(
+        value,
+        Value::Array(vec![
+            Value::Number(16_777_217u64.into()),
+            Value::Number((-9_007_199_254_740_993i64).into()),
+            Value::Number(Number::from_f64(0.5).unwrap()),
+        ])
+    );
+
+    let iterative = ParseOptions {
+        iterative: true,
+        ..Default::default()
+    };
+    for opts in &[ParseOptions::default(), iterative] {
+        let res = opts.parse_str("[1, -1e400]");
+        if cfg!(feature = "arbitrary_precision") {
+            assert_eq!(res.unwrap()[1].to_string(), "-1e400");
+            continue;
+        }
+        match res {
+            Err(Error::Parse(e)) => {
+                assert_eq!(
+                    (e.kind(), e.offset()),
+                    (&ParseErrorKind::NumberOutOfRange, 4)
+                );
+                assert_eq!(
+                    e.to_string(),
+                    "number out of range at line 1, column 5 in array"
+                );
+            }
+            res => panic!("{:?}", res),
+        }
+    }
+}
+
+#[test]
+fn strict_test() {
+    let strict = ParseOptions::strict();
+    for lenient in &["+1", ".5", "1.", "\"\u{0001}\""] {
+        assert!(from_str(lenient).is_ok(), "{}", lenient);
+        assert!(strict.parse_str(lenient).is_err(), "{}", lenient);
+    }
+    for json in &["-0", "1E+2", "0.5e-3", "[ ]", "\"\u{007f}\""] {
+        assert!(strict.parse_str(json).is_ok(), "{}", json);
+    }
+}
+
+#[test]
+fn escape_test() {
+    let opts = ParseOptions::default();
+    let (_, s) = js_string(r#""\"\\\/\b\f\n\r\t\u0041""#, &opts).unwrap();
+    assert_eq!(s, "\"\\/\u{8}\u{c}\n\r\tA");
+
+    let (_, s) = js_string(r#""\ud83d\ude00""#, &opts).unwrap();
+    assert_eq!(s, "\u{1f600}");
+}
+
+#[test]
+fn lone_surrogate_test() {
+    let reject = ParseOptions::default();
+    let replace = ParseOptions {
+        lone_surrogates: LoneSurrogates::Replace,
+        ..Default::default()
+    };
+
+    for input in &[
+        r#""\ud83d""#,
+        r#""\ude00""#,
+        r#""\ud83d\u0041""#,
+        r#""\ude00\ud83d""#,
+    ] {
+        assert!(js_string(input, &reject).is_err(), "{}", input);
+    }
+
+    let (_, s) = js_string(r#""a\ud83dA\ude00""#, &replace).unwrap();
+    assert_eq!(s, "a\u{fffd}A\u{fffd}");
+
+    let keep = ParseOptions {
+        lone_surrogates: LoneSurrogates::Keep,
+        ..Default::default()
+    };
+    let s = wtf8_string(r#" "\ud83d\ud83d\ude00\udc00" "#, &keep).unwrap();
+    assert_eq!(s, b"\xed\xa0\xbd\xf0\x9f\x98\x80\xed\xb0\x80");
+    let s = wtf8_string(r#""\ud83d\ud83d\ude00""#, &replace).unwrap();
+    assert_eq!(s, "\u{fffd}\u{1f600}".as_bytes());
+
+    let e = wtf8_string(r#""\ud83d""#, &reject).unwrap_err();
+    assert_eq!(e.expected(), &["low surrogate escape"]);
+    assert_eq!(e.offset(), 7);
+    let e = wtf8_string(r#""a" 1"#, &keep).unwrap_err();
+    assert_eq!(e.expected(), &["end of input"]);
+
+    // Strings can't hold them
+    assert!(js_string(r#""\ud83d""#, &keep).is_err());
+    assert!(keep.parse_str(r#"["\ude00"]"#).is_err());
+}
+
+#[test]
+fn error_test() {
+    let parse_err = |s| match from_str(s) {
+        Err(Error::Parse(e)) => e,
+        res => panic!("{:?}", res),
+    };
+
+    let err = parse_err("{\n  \"a\": [1, tru]\n}");
+    assert_eq!(err.offset(), 13);
+    assert_eq!((err.line(), err.column()), (2, 12));
+    assert_eq!(err.expected(), &["value"]);
+    assert_eq!(err.found(), "`tru`");
+    assert_eq!(err.context(), &["array", "object item", "object"]);
+
+    let err = parse_err("[1 2]");
+    assert_eq!(err.expected(), &["','", "']'"]);
+}
+
+#[test]
+fn from_str_test() {
+    assert_eq!(
+        from_str(" [1] ").unwrap(),
+        Value::Array(vec![Value::Number(1.into())])
+    );
+    assert_eq!(from_slice(b"null").unwrap(), Value::Null);
+    assert_eq!(from_reader(&b"true\n"[..]).unwrap(), Value::Boolean(true));
+
+    match from_str("[1] x") {
+        Err(Error::Parse(e)) => {
+            assert_eq!(e.offset(), 4);
+            assert_eq!(e.expected(), &["end of input"]);
+        }
+        res => panic!("{:?}", res),
+    }
+
+    match from_slice(b"[\"a\",\n \"\xff\"]") {
+        Err(Error::Parse(e)) => {
+            assert_eq!(
+                e.kind(),
+                &ParseErrorKind::InvalidEncoding {
+                    encoding: Encoding::Utf8
+                }
+            );
+            assert_eq!((e.offset(), e.line(), e.column()), (8, 2, 3));
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn bom_test() {
+    let text = "\u{feff}{\"a\": [1]}";
+    let expected = from_str("{\"a\": [1]}").unwrap();
+    let reject = ParseOptions {
+        bom: Bom::Reject,
+        ..Default::default()
+    };
+
+    for opts in &[ParseOptions::default(), reject.clone()] {
+        let iterative = ParseOptions {
+            iterative: true,
+            ..opts.clone()
+        };
+        for opts in &[opts, &iterative] {
+            match opts.parse_str(text) {
+                Ok(value) => {
+                    assert_eq!(opts.bom, Bom::Skip);
+                    assert_eq!(value, expected);
+                }
+                Err(Error::Parse(e)) => {
+                    assert_eq!(opts.bom, Bom::Reject);
+                    assert_eq!(e.offset(), 0);
+                    assert_eq!(e.expected(), &["value"]);
+                }
+                res => panic!("{:?}", res),
+            }
+        }
+    }
+
+    // Errors count the mark as part of the input
+    assert_eq!(
+        from_str("\u{feff}[1 2]").unwrap_err().to_string(),
+        "expected ',' or ']', found `2` at line 1, column 5 in array"
+    );
+    assert!(from_str("\u{feff}").is_err());
+    assert!(from_str("[\u{feff}]").is_err());
+
+    let utf16: Vec<u8> = "\u{feff}[\"\u{e9}\"]"
+        .encode_utf16()
+        .flat_map(|u| u.to_le_bytes().to_vec())
+        .collect();
+    assert!(from_slice(&utf16).is_err());
+    let detect = ParseOptions {
+        detect_encoding: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        detect.parse_slice(&utf16).unwrap(),
+        Value::Array(vec![Value::String("\u{e9}".into())])
+    );
+}
+
+#[test]
+fn object_order_test() {
+    let text = r#"{"z":1,"a":2,"m":{"y":null,"b":true}}"#;
+    let value = from_str(text).unwrap();
+    assert_eq!(to_string(&value), text);
+
+    let sorted = WriteOptions {
+        sort_keys: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        sorted.to_string(&value),
+        r#"{"a":2,"m":{"b":true,"y":null},"z":1}"#
+    );
+}
+
+#[test]
+fn duplicate_keys_test() {
+    let text = "{\"a\": 1, \"b\": 2,\n \"a\": 3}";
+    let with = |duplicate_keys| {
+        ParseOptions {
+            duplicate_keys,
+            ..Default::default()
+        }
+        .parse_str(text)
+    };
+
+    let expected = |a| {
+        let mut map = Map::new();
+        map.insert("a".to_string(), Value::Number(Number::from(a)));
+        map.insert("b".to_string(), Value::Number(Number::from(2)));
+        Value::Object(map)
+    };
+    assert_eq!(with(DuplicateKeys::LastWins).unwrap(), expected(3));
+    assert_eq!(with(DuplicateKeys::FirstWins).unwrap(), expected(1));
+
+    let all = with(DuplicateKeys::KeepAll).unwrap();
+    assert_eq!(to_string(&all), r#"{"a":1,"b":2,"a":3}"#);
+
+    match with(DuplicateKeys::Error) {
+        Err(Error::Parse(e)) => {
+            assert_eq!(e.position().line, 2);
+            assert_eq!(
+                e.kind(),
+                &ParseErrorKind::DuplicateKey {
+                    key: "a".into(),
+                    first: Position {
+                        offset: 1,
+                        line: 1,
+                        column: 2
+                    }
+                }
+            );
+            assert_eq!(
+                e.to_string(),
+                "duplicate key \"a\", first defined at line 1, column 2 at line 2, column 2 in object"
+            );
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn depth_test() {
+    let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+    let iterative = ParseOptions {
+        iterative: true,
+        ..Default::default()
+    };
+
+    assert!(from_str(&nested(128)).is_ok());
+    for opts in &[ParseOptions::default(), iterative.clone()] {
+        match opts.parse_str(&format!("{{\"a\": {}}}", nested(200))) {
+            Err(Error::Parse(e)) => {
+                assert_eq!(e.kind(), &ParseErrorKind::TooDeep { max_depth: 128 });
+                assert_eq!(e.column(), 134);
+                assert_eq!(
+                    e.to_string(),
+                    "nesting deeper than 128 levels at line 1, column 134 in array"
+                );
+            }
+            res => panic!("{:?}", res),
+        }
+    }
+
+    let unlimited = ParseOptions {
+        max_depth: None,
+        ..iterative
+    };
+    let deep = unlimited.parse_str(&nested(100_000)).unwrap();
+    deep.drop_iteratively();
+
+    let text = format!("[{}]", "{\"a\": [".repeat(50_000) + &"]}".repeat(50_000));
+    let deep = unlimited.parse_borrowed(&text).unwrap();
+    deep.drop_iteratively();
+
+    // Nor may the parser recurse when it gives up on such values, whether they are
+    // finished or not
+    for text in &[nested(100_000) + "x", format!("[{} x", nested(100_000))] {
+        assert!(unlimited.parse_str(text).is_err());
+        assert!(unlimited.parse_borrowed(text).is_err());
+    }
+}
+
+#[test]
+fn iterative_test() {
+    let opts = |duplicate_keys| ParseOptions {
+        duplicate_keys,
+        ..Default::default()
+    };
+    let iterative = |duplicate_keys| ParseOptions {
+        iterative: true,
+        ..opts(duplicate_keys)
+    };
+
+    for text in &[
+        include_str!("../example.json"),
+        "{\"a\": 1,\n \"b\": [{}], \"a\": [3]}",
+        "[1, {\"a\" 2}]",
+        " \"\\u00e9\" x",
+    ] {
+        for &policy in &[
+            DuplicateKeys::Error,
+            DuplicateKeys::FirstWins,
+            DuplicateKeys::LastWins,
+            DuplicateKeys::KeepAll,
+        ] {
+            let expected = opts(policy).parse_str(text).map_err(|e| e.to_string());
+            let actual = iterative(policy).parse_str(text).map_err(|e| e.to_string());
+            assert_eq!(actual, expected, "{:?} {:?}", text, policy);
+        }
+    }
+}
+
+#[test]
+fn limits_test() {
+    let cases: &[(ParseOptions, &str, ParseErrorKind, usize)] = &[
+        (
+            ParseOptions {
+                max_input_len: Some(7),
+                ..Default::default()
+            },
+            "[\"\u{e9}\u{e9}\u{e9}\u{e9}\"]",
+            ParseErrorKind::InputTooLong { max_input_len: 7 },
+            6,
+        ),
+        (
+            ParseOptions {
+                max_string_len: Some(3),
+                ..Default::default()
+            },
+            "{\"ab\": \"ab\\u00e9\"}",
+            ParseErrorKind::StringTooLong { max_string_len: 3 },
+            7,
+        ),
+        (
+            ParseOptions {
+                max_string_len: Some(3),
+                ..Default::default()
+            },
+            "{\"abcd\": 1}",
+            ParseErrorKind::StringTooLong { max_string_len: 3 },
+            1,
+        ),
+        (
+            ParseOptions {
+                max_number_digits: Some(4),
+                ..Default::default()
+            },
+            "[1.5, -12.5e-10]",
+            ParseErrorKind::NumberTooLong {
+                max_number_digits: 4,
+            },
+            6,
+        ),
+        (
+            ParseOptions {
+                max_items: Some(2),
+                ..Default::default()
+            },
+            "[[1, 2], {\"a\": 1, \"b\": 2, \"c\": 3}]",
+            ParseErrorKind::TooManyItems { max_items: 2 },
+            26,
+        ),
+        (
+            ParseOptions {
+                max_nodes: Some(4),
+                ..Default::default()
+            },
+            "[[1, 2], {\"a\": 3}]",
+            ParseErrorKind::TooManyNodes { max_nodes: 4 },
+            9,
+        ),
+    ];
+
+    for (opts, text, kind, offset) in cases {
+        let iterative = ParseOptions {
+            iterative: true,
+            ..opts.clone()
+        };
+        for opts in &[opts, &iterative] {
+            match opts.parse_str(text) {
+                Err(Error::Parse(e)) => {
+                    assert_eq!((e.kind(), e.offset()), (kind, *offset), "{:?}", text)
+                }
+                res => panic!("{:?}: {:?}", text, res),
+            }
+        }
+        assert_eq!(
+            opts.parse_slice(text.as_bytes()).unwrap_err().to_string(),
+            opts.parse_str(text).unwrap_err().to_string()
+        );
+    }
+
+    let opts = ParseOptions {
+        max_input_len: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(
+        opts.parse_str("[1]").unwrap(),
+        Value::Array(vec![Value::Number(1.into())])
+    );
+    // Reading stops at the limit, and what comes after it isn't looked at
+    let mut reader = std::io::Cursor::new(b"[1, 2]\xff\xff".to_vec());
+    match opts.parse_reader(&mut reader) {
+        Err(Error::Parse(e)) => assert_eq!(e.offset(), 3),
+        res => panic!("{:?}", res),
+    }
+    assert_eq!(reader.position(), 4);
+}
+
+#[test]
+fn jsonc_test() {
+    let text = "// Settings\n{\n  \"a\": [1, /* two */ 2,],\n  \"b\" /* key */ : {\"c\": null,}, // last\n}\n";
+    let expected = from_str(r#"{"a": [1, 2], "b": {"c": null}}"#).unwrap();
+    let iterative = ParseOptions {
+        iterative: true,
+        ..ParseOptions::jsonc()
+    };
+
+    for opts in &[ParseOptions::jsonc(), iterative] {
+        assert_eq!(opts.parse_str(text).unwrap(), expected);
+        assert_eq!(
+            opts.parse_str("[1] // end").unwrap(),
+            from_str("[1]").unwrap()
+        );
+
+        let err = |text| match opts.parse_str(text) {
+            Err(Error::Parse(e)) => (e.offset(), e.expected().to_vec()),
+            res => panic!("{:?}", res),
+        };
+        assert_eq!(err("[1, /* open"), (11, vec!["'*/'"]));
+        assert_eq!(err("[1,,]"), (3, vec!["']'", "value"]));
+        assert_eq!(err("{\"a\": 1,,}"), (8, vec!["'}'", "string"]));
+        assert_eq!(err("[,]"), (1, vec!["']'", "value"]));
+        assert_eq!(err("[1 / 2]"), (3, vec!["','", "']'"]));
+    }
+
+    // Neither is allowed by default
+    assert!(from_str("[1, 2,]").is_err());
+    assert!(from_str("[1] // end").is_err());
+    let comments = ParseOptions {
+        allow_comments: true,
+        ..Default::default()
+    };
+    assert!(comments.parse_str("[1, /* 2 */]").is_err());
+}
+
+#[test]
+fn json5_test() {
+    let text = r#"// From the JSON5 home page
+{
+  // comments
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use "double quotes" here',
+  lineBreaks: "Look, Mom! \
+No \\n's!",
+  hexadecimal: 0xdecaF,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  trailingComma: 'in objects', andIn: ['arrays',],
+  "backwardsCompatible": "with JSON",
+}"#;
+    let expected = from_str(
+        r#"{
+  "unquoted": "and you can quote me on that",
+  "singleQuotes": "I can use \"double quotes\" here",
+  "lineBreaks": "Look, Mom! No \\n's!",
+  "hexadecimal": 912559,
+  "leadingDecimalPoint": 0.8675309, "andTrailing": 8675309.0,
+  "positiveSign": 1,
+  "trailingComma": "in objects", "andIn": ["arrays"],
+  "backwardsCompatible": "with JSON"
+}"#,
+    )
+    .unwrap();
+    let iterative = ParseOptions {
+        iterative: true,
+        ..ParseOptions::json5()
+    };
+
+    for opts in &[ParseOptions::json5(), iterative] {
+        assert_eq!(opts.parse_str(text).unwrap(), expected);
+
+        let value = opts
+            .parse_str("{$_caf\\u00e9\u{a0}:\u{2028}['\\x41\\v\\0\\q', -Infinity, NaN, -0x10]}")
+            .unwrap();
+        let obj = match &value {
+            Value::Object(obj) => obj,
+            _ => panic!("{:?}", value),
+        };
+        let arr = match obj.get("$_caf\u{e9}") {
+            Some(Value::Array(arr)) => arr,
+            _ => panic!("{:?}", value),
+        };
+        assert_eq!(arr[0], Value::String("A\u{b}\0q".into()));
+        assert!(matches!(&arr[1], Value::Number(n) if n.to_f64() == f64::NEG_INFINITY));
+        assert!(matches!(&arr[2], Value::Number(n) if n.to_f64().is_nan()));
+        assert_eq!(arr[3], Value::Number(Number::from(-16)));
+        assert_eq!(
+            to_string(&value),
+            "{\"$_caf\u{e9}\":[\"A\\u000b\\u0000q\",null,null,-16]}"
+        );
+
+        let err = |text| match opts.parse_str(text) {
+            Err(Error::Parse(e)) => (e.offset(), e.expected().to_vec()),
+            res => panic!("{:?}", res),
+        };
+        assert_eq!(err("'a\nb'"), (2, vec!["closing \"'\""]));
+        assert_eq!(err("'\\1'"), (2, vec!["escape sequence"]));
+        assert_eq!(err("{1: 2}"), (1, vec!["'}'", "key"]));
+        assert_eq!(err("{a\\u0020b: 2}"), (2, vec!["identifier character"]));
+        assert_eq!(err("[0x]"), (2, vec!["','", "']'"]));
+
+        let limited = ParseOptions {
+            max_number_digits: Some(4),
+            ..opts.clone()
+        };
+        assert!(limited.parse_str("[0xFfFf, -0x1234]").is_ok());
+        match limited.parse_str(&format!("[1, 0x{}]", "F".repeat(100))) {
+            Err(Error::Parse(e)) => assert_eq!(
+                (e.kind(), e.offset()),
+                (
+                    &ParseErrorKind::NumberTooLong {
+                        max_number_digits: 4
+                    },
+                    4
+                )
+            ),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    // JSON5 syntax is only accepted when asked for
+    assert!(from_str("{a: 1}").is_err());
+    assert!(from_str("'a'").is_err());
+    assert!(from_str("0x10").is_err());
+    assert!(from_str("\u{a0}1").is_err());
+}
@@ -0,0 +1,162 @@
+/// Builds a [`Value`](crate::Value) from JSON-like syntax.
+///
+/// Anything that isn't JSON syntax is a Rust expression, turned into a value with
+/// `Value::from`. Keys are string literals, or any expression that converts into
+/// `String` put in parentheses.
+///
+/// ```
+/// use json_rs_prac::json;
+///
+/// let name = "bar";
+/// let key = String::from("n");
+/// let value = json!({
+///     "abc": "def",
+///     "foo": [name, 123, null, { (key): 1 + 2 }],
+///     "opt": Some(true),
+/// });
+/// assert_eq!(
+///     value.to_string(),
+///     r#"{"abc":"def","foo":["bar",123,null,{"n":3}],"opt":true}"#
+/// );
+/// ```
+#[macro_export]
+macro_rules! json {
+    ($($json:tt)+) => {
+        $crate::json_internal!($($json)+)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! json_internal {
+    // Arrays: the tokens of the current element are collected up to the next comma,
+    // single token elements are taken right away to keep the recursion shallow
+    (@array [$($elems:expr,)*] ()) => {
+        vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] () $elem:tt) => {
+        vec![$($elems,)* $crate::json_internal!($elem)]
+    };
+    (@array [$($elems:expr,)*] () $elem:tt , $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!($elem),] () $($rest)*)
+    };
+    (@array [$($elems:expr,)*] ($($elem:tt)+)) => {
+        vec![$($elems,)* $crate::json_internal!($($elem)+)]
+    };
+    (@array [$($elems:expr,)*] ($($elem:tt)+) , $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)* $crate::json_internal!($($elem)+),] () $($rest)*)
+    };
+    (@array [$($elems:expr,)*] ($($elem:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elems,)*] ($($elem)* $next) $($rest)*)
+    };
+
+    // Objects: members are inserted into `$object` one by one, values are collected
+    // like array elements
+    (@object $object:ident) => {};
+    (@object $object:ident $key:tt : $value:tt) => {
+        $object.insert($key.into(), $crate::json_internal!($value));
+    };
+    (@object $object:ident $key:tt : $value:tt , $($rest:tt)*) => {
+        $object.insert($key.into(), $crate::json_internal!($value));
+        $crate::json_internal!(@object $object $($rest)*);
+    };
+    (@object $object:ident $key:tt : $($rest:tt)+) => {
+        $crate::json_internal!(@member $object $key () $($rest)+);
+    };
+    (@member $object:ident $key:tt ($($value:tt)+)) => {
+        $object.insert($key.into(), $crate::json_internal!($($value)+));
+    };
+    (@member $object:ident $key:tt ($($value:tt)+) , $($rest:tt)*) => {
+        $object.insert($key.into(), $crate::json_internal!($($value)+));
+        $crate::json_internal!(@object $object $($rest)*);
+    };
+    (@member $object:ident $key:tt ($($value:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::json_internal!(@member $object $key ($($value)* $next) $($rest)*);
+    };
+
+    (null) => {
+        $crate::Value::Null
+    };
+    (true) => {
+        $crate::Value::Boolean(true)
+    };
+    (false) => {
+        $crate::Value::Boolean(false)
+    };
+    ([]) => {
+        $crate::Value::Array(vec![])
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::Value::Array($crate::json_internal!(@array [] () $($tt)+))
+    };
+    ({}) => {
+        $crate::Value::Object($crate::Map::new())
+    };
+    ({ $($tt:tt)+ }) => {
+        $crate::Value::Object({
+            let mut object = $crate::Map::new();
+            $crate::json_internal!(@object object $($tt)+);
+            object
+        })
+    };
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+#[test]
+fn json_test() {
+    use crate::Value;
+
+    let x = 5;
+    let items = vec!["a", "b"];
+    let value = json!({
+        "null": null,
+        "bools": [true, false,],
+        "expr": x * 2 + 1,
+        "neg": -1,
+        "nested": {"a": [[], {}, [1, [2]]]},
+        ("dyn".to_string() + "amic"): items.clone(),
+        "call": items.len(),
+        "last": Value::Null
+    });
+    assert_eq!(
+        value,
+        crate::from_str(
+            r#"{"null": null, "bools": [true, false], "expr": 11, "neg": -1,
+                "nested": {"a": [[], {}, [1, [2]]]}, "dynamic": ["a", "b"], "call": 2,
+                "last": null}"#
+        )
+        .unwrap()
+    );
+
+    assert_eq!(json!(null), Value::Null);
+    assert_eq!(json!([x, -x, "s"]).to_string(), "[5,-5,\"s\"]");
+    assert_eq!(json!({}).to_string(), "{}");
+}
+
+#[test]
+fn explicit_test() {
+    use crate::{Map, Number, Value};
+
+    let mut inner = Map::new();
+    inner.insert("b".to_string(), Value::Array(vec![]));
+    let mut expected = Map::new();
+    expected.insert("abc".to_string(), Value::String("def".into()));
+    expected.insert(
+        "foo".to_string(),
+        Value::Array(vec![
+            Value::String("bar".into()),
+            Value::Number(Number::from(123)),
+            Value::Null,
+            Value::Object(inner),
+        ]),
+    );
+    expected.insert("t".to_string(), Value::Boolean(true));
+
+    assert_eq!(
+        json!({"abc": "def", "foo": ["bar", 123, null, {"b": []}], "t": true}),
+        Value::Object(expected)
+    );
+    assert_eq!(json!(-1.5), Value::Number(Number::from_f64(-1.5).unwrap()));
+}
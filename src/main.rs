@@ -1,13 +1,24 @@
 use std::env::args;
 use std::path::Path;
+use std::process::exit;
 
-fn main() {
-    if let Some(file) = args().skip(1).filter(|s| Path::new(s).exists()).next() {
-        let content = std::fs::read_to_string(file).unwrap();
+use json_rs_prac::Error;
 
-        let value = json_rs_prac::value(&content).unwrap().1;
+fn main() {
+    if let Some(file) = args().skip(1).find(|s| Path::new(s).exists()) {
+        let content = std::fs::read_to_string(&file).unwrap();
 
-        println!("{:#?}", value);
+        match json_rs_prac::from_str(&content) {
+            Ok(value) => println!("{:#?}", value),
+            Err(Error::Parse(e)) => {
+                eprintln!("{}: {}\n{}", file, e, e.snippet());
+                exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                exit(1);
+            }
+        }
     } else {
         println!("Usage json-rs-prac [file path]");
     }
@@ -0,0 +1,398 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::{slice, vec};
+
+use crate::Value;
+
+/// Map that remembers the order its keys were inserted in, used for JSON objects.
+///
+/// Entries live in a `Vec` and a `HashMap` indexes them by key, so lookups stay O(1)
+/// while iteration follows insertion order. Removing an entry shifts the ones after it.
+///
+/// A key can also be stored more than once with [`append`](Map::append), which is how
+/// [`DuplicateKeys::KeepAll`](crate::DuplicateKeys::KeepAll) keeps every member of an
+/// object. Lookups then see the last entry for the key.
+#[derive(Clone)]
+pub struct Map<K = String, V = Value> {
+    entries: Vec<(K, V)>,
+    /// Position of the last entry for each key
+    index: HashMap<K, usize>,
+    /// Whether `append` ever stored a key twice
+    duplicates: bool,
+}
+
+impl<K, V> Map<K, V> {
+    pub fn new() -> Self {
+        Map {
+            entries: Vec::new(),
+            index: HashMap::new(),
+            duplicates: false,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Map {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+            duplicates: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+        self.duplicates = false;
+    }
+
+    /// Iterates over the entries in insertion order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.entries.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.entries.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Map<K, V> {
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.get(key).copied()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.contains_key(key)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.position(key).map(|pos| &self.entries[pos].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.position(key) {
+            Some(pos) => Some(&mut self.entries[pos].1),
+            None => None,
+        }
+    }
+
+    /// Position of the last entry for `key` in iteration order.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.position(key)
+    }
+
+    /// Every value stored under `key`, in insertion order.
+    pub fn get_all<'a, Q>(&'a self, key: &'a Q) -> impl Iterator<Item = &'a V> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entries: &[(K, V)] = match self.position(key) {
+            None => &[],
+            Some(pos) if !self.duplicates => &self.entries[pos..=pos],
+            Some(_) => &self.entries,
+        };
+
+        entries
+            .iter()
+            .filter(move |(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Inserts `value` under `key`.
+    ///
+    /// A new key goes to the end of the map. An existing key keeps its position and
+    /// its old value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.position(&key) {
+            Some(pos) => Some(std::mem::replace(&mut self.entries[pos].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Adds an entry at the end of the map even if `key` is already present.
+    pub fn append(&mut self, key: K, value: V) {
+        match self.index.get_mut(&key) {
+            Some(pos) => {
+                *pos = self.entries.len();
+                self.duplicates = true;
+            }
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+            }
+        }
+        self.entries.push((key, value));
+    }
+
+    /// Removes every entry for `key`, keeping the order of the remaining entries.
+    ///
+    /// Returns the value lookups would have seen.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = self.index.remove(key)?;
+        let value = self.remove_at(pos);
+
+        if self.duplicates {
+            while let Some(pos) = self.entries.iter().rposition(|(k, _)| k.borrow() == key) {
+                self.remove_at(pos);
+            }
+        }
+
+        Some(value)
+    }
+
+    fn remove_at(&mut self, pos: usize) -> V {
+        let (_, value) = self.entries.remove(pos);
+
+        for (idx, (k, _)) in self.entries.iter().enumerate().skip(pos) {
+            if let Some(last) = self.index.get_mut::<K>(k) {
+                if *last == idx + 1 {
+                    *last = idx;
+                }
+            }
+        }
+
+        value
+    }
+
+    /// Sorts the entries by key.
+    pub fn sort_keys(&mut self)
+    where
+        K: Ord,
+    {
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        for (pos, (k, _)) in self.entries.iter().enumerate() {
+            *self.index.get_mut(k).unwrap() = pos;
+        }
+    }
+}
+
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Map::new()
+    }
+}
+
+/// Two maps are equal if they have the same entries, in any order. Values appended
+/// under the same key have to be in the same order, though.
+impl<K: Hash + Eq + Clone, V: PartialEq> PartialEq for Map<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        if self.duplicates || other.duplicates {
+            self.keys().all(|k| self.get_all(k).eq(other.get_all(k)))
+        } else {
+            self.iter().all(|(k, v)| other.get(k) == Some(v))
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Map<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> FromIterator<(K, V)> for Map<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Extend<(K, V)> for Map<K, V> {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+pub struct Iter<'a, K, V>(slice::Iter<'a, (K, V)>);
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V>(slice::IterMut<'a, (K, V)>);
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+pub struct IntoIter<K, V>(vec::IntoIter<(K, V)>);
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> IntoIterator for Map<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.entries.into_iter())
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a Map<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut Map<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[test]
+fn order_test() {
+    let mut map: Map<&str, i32> = vec![("b", 1), ("a", 2), ("c", 3)].into_iter().collect();
+    assert_eq!(map.insert("a", 4), Some(2));
+    assert_eq!(map.insert("d", 5), None);
+    assert_eq!(
+        map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+        vec![("b", 1), ("a", 4), ("c", 3), ("d", 5)]
+    );
+
+    assert_eq!(map.remove("a"), Some(4));
+    assert_eq!(map.get("c"), Some(&3));
+    assert_eq!(map.get("d"), Some(&5));
+    assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec!["b", "c", "d"]);
+
+    map.insert("a", 6);
+    map.sort_keys();
+    assert_eq!(
+        map.keys().copied().collect::<Vec<_>>(),
+        vec!["a", "b", "c", "d"]
+    );
+    assert_eq!(map.get("b"), Some(&1));
+}
+
+#[test]
+fn eq_test() {
+    let a: Map<&str, i32> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    let b: Map<&str, i32> = vec![("b", 2), ("a", 1)].into_iter().collect();
+    let c: Map<&str, i32> = vec![("b", 2), ("a", 3)].into_iter().collect();
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn duplicate_test() {
+    let mut map = Map::new();
+    map.append("a", 1);
+    map.append("b", 2);
+    map.append("a", 3);
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get("a"), Some(&3));
+    assert_eq!(map.get_index_of("a"), Some(2));
+    assert_eq!(map.get_all("a").copied().collect::<Vec<_>>(), vec![1, 3]);
+
+    let mut other = Map::new();
+    other.append("b", 2);
+    other.append("a", 1);
+    other.append("a", 3);
+    assert_eq!(map, other);
+
+    assert_eq!(map.remove("a"), Some(3));
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get("b"), Some(&2));
+    assert_eq!(map.get_all("a").count(), 0);
+}
@@ -0,0 +1,331 @@
+//! Newline-delimited JSON, also known as JSON Lines: one value per line, as in log
+//! streams.
+//!
+//! ```
+//! use json_rs_prac::ndjson::{Reader, Writer};
+//!
+//! let input = "{\"level\": \"info\"}\n{\"level\": \"warn\"}\n";
+//! let values = Reader::new(input.as_bytes())
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//!
+//! let mut writer = Writer::new(Vec::new());
+//! for value in &values {
+//!     writer.write(value).unwrap();
+//! }
+//! assert_eq!(writer.into_inner(), b"{\"level\":\"info\"}\n{\"level\":\"warn\"}\n");
+//! ```
+
+use std::io::{self, BufRead, Write};
+use std::mem;
+
+use crate::{Bom, Error, ParseError, ParseOptions, Position, Value, WriteOptions};
+
+/// What a [`Reader`] does with lines that aren't valid JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BadLines {
+    /// Return the error, reading goes on with the next line.
+    #[default]
+    Report,
+    /// Leave them out.
+    Skip,
+    /// Leave them out, keeping their errors for [`Reader::errors`].
+    Collect,
+}
+
+/// Iterator over the values of a stream with one JSON value per line.
+///
+/// Lines end with `\n` or `\r\n`, blank lines are left out. Positions in errors are
+/// counted from the start of the stream, so their line is the line of the stream.
+/// Reading ends after an I/O error.
+pub struct Reader<R> {
+    reader: R,
+    opts: ParseOptions,
+    bad_lines: BadLines,
+    errors: Vec<ParseError>,
+    /// Where the next line starts.
+    position: Position,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Reader::with_options(reader, ParseOptions::default())
+    }
+
+    /// Each line is parsed with `opts`, [`max_input_len`](ParseOptions::max_input_len)
+    /// limits the length of a line. The input has to be UTF-8,
+    /// [`detect_encoding`](ParseOptions::detect_encoding) is ignored.
+    pub fn with_options(reader: R, opts: ParseOptions) -> Self {
+        Reader {
+            reader,
+            opts: ParseOptions {
+                detect_encoding: false,
+                ..opts
+            },
+            bad_lines: BadLines::default(),
+            errors: Vec::new(),
+            position: Position::START,
+            buf: Vec::new(),
+            done: false,
+        }
+    }
+
+    pub fn bad_lines(mut self, bad_lines: BadLines) -> Self {
+        self.bad_lines = bad_lines;
+        self
+    }
+
+    /// Errors of the lines left out so far with [`BadLines::Collect`].
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Takes the errors collected so far.
+    pub fn take_errors(&mut self) -> Vec<ParseError> {
+        mem::take(&mut self.errors)
+    }
+
+    /// Where the next line starts.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Reads the next line into `buf`, see [`read_until`].
+    fn read_line(&mut self) -> io::Result<usize> {
+        let limit = self.opts.max_input_len.map_or(usize::MAX, |max| max + 2);
+        read_until(
+            &mut self.reader,
+            b'\n',
+            limit,
+            &mut self.buf,
+            &mut self.position,
+        )
+    }
+}
+
+/// Reads up to and including the next `delim` into `buf`, keeping no more than `limit`
+/// bytes, which is enough to tell that the part is too long. Returns how many bytes the
+/// part takes up in the input, 0 at the end of the input, and moves `position` past them.
+pub(crate) fn read_until(
+    reader: &mut impl BufRead,
+    delim: u8,
+    limit: usize,
+    buf: &mut Vec<u8>,
+    position: &mut Position,
+) -> io::Result<usize> {
+    buf.clear();
+    let mut len = 0;
+
+    loop {
+        let chunk = match reader.fill_buf() {
+            Ok(chunk) => chunk,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if chunk.is_empty() {
+            return Ok(len);
+        }
+
+        let (used, end) = match chunk.iter().position(|&b| b == delim) {
+            Some(pos) => (pos + 1, true),
+            None => (chunk.len(), false),
+        };
+        let room = limit.saturating_sub(buf.len());
+        buf.extend_from_slice(&chunk[..used.min(room)]);
+        *position = position.advance_bytes(&chunk[..used]);
+        reader.consume(used);
+        len += used;
+        if end {
+            return Ok(len);
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let start = self.position;
+            let len = match self.read_line() {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            };
+
+            let mut line = &self.buf[..];
+            // A line that was cut short is too long anyway, its end doesn't matter
+            if line.len() == len {
+                line = line.strip_suffix(b"\n").unwrap_or(line);
+                line = line.strip_suffix(b"\r").unwrap_or(line);
+            }
+            if line.iter().all(|b| matches!(b, b' ' | b'\t' | b'\r')) {
+                continue;
+            }
+
+            let res = self.opts.parse_slice(line);
+            // Only the stream as a whole may start with a byte order mark
+            self.opts.bom = Bom::Reject;
+            match res {
+                Ok(value) => return Some(Ok(value)),
+                Err(Error::Parse(e)) => {
+                    let e = e.rebase(start);
+                    match self.bad_lines {
+                        BadLines::Report => return Some(Err(Error::Parse(e))),
+                        BadLines::Skip => {}
+                        BadLines::Collect => self.errors.push(e),
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        self.done = true;
+        None
+    }
+}
+
+/// Writes values as compact JSON, one per line.
+pub struct Writer<W> {
+    writer: W,
+    opts: WriteOptions,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Writer::with_options(writer, WriteOptions::default())
+    }
+
+    /// [`indent`](WriteOptions::indent) is ignored, every value takes up one line.
+    pub fn with_options(writer: W, opts: WriteOptions) -> Self {
+        Writer {
+            writer,
+            opts: WriteOptions {
+                indent: None,
+                ..opts
+            },
+        }
+    }
+
+    pub fn write(&mut self, value: &Value) -> io::Result<()> {
+        self.opts.to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")
+    }
+
+    /// Writes any serializable value as a line, like [`write`](Writer::write) does
+    /// for a [`Value`].
+    #[cfg(feature = "serde")]
+    pub fn serialize<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        crate::ser::to_writer(&mut self.writer, value)?;
+        Ok(self.writer.write_all(b"\n")?)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[test]
+fn reader_test() {
+    let input = "\u{feff}{\"a\": 1}\r\n\n  \n[1,\n\"x\" 2\n\u{feff}3\nnull";
+    let lines: Vec<_> = Reader::new(input.as_bytes())
+        .map(|res| res.map_err(|e| e.to_string()))
+        .collect();
+
+    assert_eq!(
+        lines,
+        vec![
+            Ok(crate::from_str("{\"a\": 1}").unwrap()),
+            Err("expected value, found end of input at line 4, column 4 in array".into()),
+            Err("expected end of input, found `2` at line 5, column 5".into()),
+            Err("expected value, found '\\u{feff}' at line 6, column 1".into()),
+            Ok(Value::Null),
+        ]
+    );
+
+    let mut reader = Reader::new(input.as_bytes()).bad_lines(BadLines::Collect);
+    assert_eq!(reader.by_ref().count(), 2);
+    let lines: Vec<_> = reader.errors().iter().map(|e| e.line()).collect();
+    assert_eq!(lines, [4, 5, 6]);
+    assert_eq!(reader.position().offset, input.len());
+
+    let reader = Reader::new(input.as_bytes()).bad_lines(BadLines::Skip);
+    assert_eq!(reader.count(), 2);
+}
+
+#[test]
+fn long_line_test() {
+    let opts = ParseOptions {
+        max_input_len: Some(4),
+        ..Default::default()
+    };
+    let input = "[1]\r\n[12]\r\n[123]\n[1234567]\n\"\u{e9}\"";
+    let lines: Vec<_> = Reader::with_options(input.as_bytes(), opts)
+        .map(|res| res.map_err(|e| e.to_string()))
+        .collect();
+
+    assert_eq!(
+        lines,
+        vec![
+            Ok(crate::from_str("[1]").unwrap()),
+            Ok(crate::from_str("[12]").unwrap()),
+            Err("input longer than 4 bytes at line 3, column 5".into()),
+            Err("input longer than 4 bytes at line 4, column 5".into()),
+            Ok(Value::String("\u{e9}".into())),
+        ]
+    );
+}
+
+#[test]
+fn writer_test() {
+    let values = vec![
+        crate::from_str("{\"b\": [1, 2], \"a\": \"x\\ny\"}").unwrap(),
+        Value::Null,
+    ];
+    let sorted = WriteOptions {
+        sort_keys: true,
+        ..WriteOptions::pretty()
+    };
+    let mut writer = Writer::with_options(Vec::new(), sorted);
+    for value in &values {
+        writer.write(value).unwrap();
+    }
+    let output = writer.into_inner();
+    assert_eq!(output, b"{\"a\":\"x\\ny\",\"b\":[1,2]}\nnull\n");
+
+    let read: Vec<_> = Reader::new(&output[..]).map(Result::unwrap).collect();
+    assert_eq!(read.len(), 2);
+    assert_eq!(read[1], Value::Null);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_test() {
+    #[derive(serde::Serialize)]
+    struct Event {
+        id: u32,
+        message: &'static str,
+    }
+
+    let mut writer = Writer::new(Vec::new());
+    writer
+        .serialize(&Event {
+            id: 1,
+            message: "a\nb",
+        })
+        .unwrap();
+    writer.serialize(&[1.5, f64::NAN]).unwrap();
+    assert_eq!(
+        writer.into_inner(),
+        b"{\"id\":1,\"message\":\"a\\nb\"}\n[1.5,null]\n"
+    );
+}
@@ -0,0 +1,483 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// A JSON number that remembers how it was written.
+///
+/// Integers are kept as `u64`/`i64` so they round-trip exactly, everything else is
+/// stored as `f64`. With the `arbitrary_precision` feature, numbers that `f64` would
+/// change keep their original decimal text instead: those out of its range, and those
+/// with more digits than the shortest form of the nearest `f64`.
+///
+/// Only [JSON5](crate::ParseOptions::json5) parsing gives numbers that aren't finite.
+#[derive(PartialEq, Clone)]
+pub struct Number {
+    n: N,
+}
+
+/// Name of the newtype struct a number kept as decimal text is serialized as, so that
+/// the `serde` support of this crate can pass the text on instead of rounding it.
+#[cfg(feature = "serde")]
+pub(crate) const TOKEN: &str = "$json_rs_prac::private::Number";
+
+#[derive(PartialEq, Clone)]
+enum N {
+    PosInt(u64),
+    /// Always less than zero.
+    NegInt(i64),
+    Float(f64),
+    #[cfg(feature = "arbitrary_precision")]
+    Decimal(String),
+}
+
+impl Number {
+    /// Returns true if the number is an integer between `0` and `u64::MAX`.
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    /// Returns true if the number is an integer between `i64::MIN` and `i64::MAX`.
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// Returns true if the number is exactly representable as `f64`.
+    pub fn is_f64(&self) -> bool {
+        self.as_f64().is_some()
+    }
+
+    /// Returns true if the number was written with a fraction or exponent
+    /// (or didn't fit into a 64-bit integer).
+    pub fn is_float(&self) -> bool {
+        !matches!(self.n, N::PosInt(_) | N::NegInt(_))
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.n {
+            N::PosInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.n {
+            N::PosInt(n) if n <= i64::MAX as u64 => Some(n as i64),
+            N::NegInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        self.as_u64().and_then(|n| u32::try_from(n).ok())
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_i64().and_then(|n| i32::try_from(n).ok())
+    }
+
+    /// Returns the number as `f64` only if no precision is lost.
+    ///
+    /// Integers beyond 2^53 that have no exact `f64` counterpart give `None`;
+    /// use [`to_f64`](Number::to_f64) for a lossy conversion.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.n {
+            N::PosInt(n) => {
+                let f = n as f64;
+                // `u64::MAX as f64` rounds up to 2^64, which saturates back to `u64::MAX`
+                if f < 18_446_744_073_709_551_616.0 && f as u64 == n {
+                    Some(f)
+                } else {
+                    None
+                }
+            }
+            N::NegInt(n) => {
+                let f = n as f64;
+                if f as i64 == n {
+                    Some(f)
+                } else {
+                    None
+                }
+            }
+            N::Float(f) => Some(f),
+            #[cfg(feature = "arbitrary_precision")]
+            N::Decimal(_) => None,
+        }
+    }
+
+    /// Converts the number to the nearest `f64`.
+    pub fn to_f64(&self) -> f64 {
+        match self.n {
+            N::PosInt(n) => n as f64,
+            N::NegInt(n) => n as f64,
+            N::Float(f) => f,
+            #[cfg(feature = "arbitrary_precision")]
+            N::Decimal(ref s) => s.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Whether both numbers have the same value however they are stored, so `1` equals
+    /// `1.0`. Integers are compared with floats exactly, without rounding them to `f64`:
+    /// 2^53 + 1 doesn't equal the float 2^53 it would round to.
+    pub(crate) fn value_eq(&self, other: &Number) -> bool {
+        match (&self.n, &other.n) {
+            #[cfg(feature = "arbitrary_precision")]
+            (N::Decimal(s), N::Float(f)) | (N::Float(f), N::Decimal(s)) => {
+                f.is_finite() && decimal_eq(s, *f)
+            }
+            #[cfg(feature = "arbitrary_precision")]
+            (N::Decimal(_), _) | (_, N::Decimal(_)) => {
+                same_decimal(&self.to_string(), &other.to_string())
+            }
+            (N::Float(f), N::PosInt(n)) | (N::PosInt(n), N::Float(f)) => {
+                f.fract() == 0.0
+                    && *f >= 0.0
+                    && *f < 18_446_744_073_709_551_616.0
+                    && *f as u64 == *n
+            }
+            (N::Float(f), N::NegInt(n)) | (N::NegInt(n), N::Float(f)) => {
+                f.fract() == 0.0 && *f >= -9_223_372_036_854_775_808.0 && *f as i64 == *n
+            }
+            _ => self == other,
+        }
+    }
+
+    /// The text of a number that `f64` would change.
+    #[cfg(all(feature = "serde", feature = "arbitrary_precision"))]
+    pub(crate) fn as_decimal(&self) -> Option<&str> {
+        match self.n {
+            N::Decimal(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns false for infinities and NaN, which only JSON5 has.
+    pub fn is_finite(&self) -> bool {
+        match self.n {
+            N::Float(f) => f.is_finite(),
+            _ => true,
+        }
+    }
+
+    /// Parses a number as JSON5 writes it, which adds hexadecimal integers, `Infinity`
+    /// and `NaN` to what [`FromStr`] takes. Hexadecimal integers too large for 64 bits
+    /// are rounded to the nearest `f64`.
+    pub(crate) fn from_json5(s: &str) -> Result<Number, ParseNumberError> {
+        let neg = s.starts_with('-');
+        let unsigned = s.trim_start_matches(['-', '+']);
+        let f = match unsigned {
+            "Infinity" => f64::INFINITY,
+            "NaN" => f64::NAN,
+            _ if unsigned.starts_with("0x") || unsigned.starts_with("0X") => {
+                let hex = &unsigned[2..];
+                match u64::from_str_radix(hex, 16) {
+                    Ok(n) if !neg => return Ok(Number { n: N::PosInt(n) }),
+                    // `-0x0` keeps its sign as a float, like `-0`
+                    Ok(n) if n != 0 && n <= i64::MIN.unsigned_abs() => {
+                        return Ok(Number {
+                            n: N::NegInt((n as i64).wrapping_neg()),
+                        })
+                    }
+                    _ => hex
+                        .chars()
+                        .filter_map(|c| c.to_digit(16))
+                        .fold(0.0, |acc, d| acc * 16.0 + f64::from(d)),
+                }
+            }
+            // `FromStr` takes the rest, rewriting it as JSON for `arbitrary_precision`
+            _ => return s.parse(),
+        };
+        Ok(Number {
+            n: N::Float(if neg { -f } else { f }),
+        })
+    }
+
+    /// Builds a number from a finite `f64`; returns `None` for NaN and infinities
+    /// since JSON can't represent them.
+    pub fn from_f64(f: f64) -> Option<Number> {
+        if f.is_finite() {
+            Some(Number { n: N::Float(f) })
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.n {
+            N::PosInt(n) => write!(f, "Number({})", n),
+            N::NegInt(n) => write!(f, "Number({})", n),
+            N::Float(n) => write!(f, "Number({:?})", n),
+            #[cfg(feature = "arbitrary_precision")]
+            N::Decimal(ref s) => write!(f, "Number({})", s),
+        }
+    }
+}
+
+/// Writes the number as JSON. Floats always get a fraction or exponent so they read
+/// back as floats. Infinities and NaN are written as in JSON5.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.n {
+            N::PosInt(n) => write!(f, "{}", n),
+            N::NegInt(n) => write!(f, "{}", n),
+            N::Float(n) if n.is_nan() => f.write_str("NaN"),
+            N::Float(n) if n.is_infinite() => {
+                f.write_str(if n < 0.0 { "-Infinity" } else { "Infinity" })
+            }
+            // `Debug` is the shortest representation that round-trips and keeps the `.0`
+            N::Float(n) => write!(f, "{:?}", n),
+            #[cfg(feature = "arbitrary_precision")]
+            N::Decimal(ref s) => f.write_str(s),
+        }
+    }
+}
+
+/// Error returned when a string is not a number.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseNumberError;
+
+impl fmt::Display for ParseNumberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid number")
+    }
+}
+
+impl std::error::Error for ParseNumberError {}
+
+fn is_integer(s: &str) -> bool {
+    let digits = s.trim_start_matches(['-', '+']);
+    s.len() - digits.len() <= 1 && !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+impl FromStr for Number {
+    type Err = ParseNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if is_integer(s) {
+            if !s.starts_with('-') {
+                if let Ok(n) = s.parse() {
+                    return Ok(Number { n: N::PosInt(n) });
+                }
+            } else if let Ok(n) = s.parse::<i64>() {
+                // `-0` has no integer representation, keep its sign as a float
+                if n < 0 {
+                    return Ok(Number { n: N::NegInt(n) });
+                }
+            }
+        }
+
+        let f: f64 = s.parse().map_err(|_| ParseNumberError)?;
+
+        #[cfg(feature = "arbitrary_precision")]
+        {
+            // `{:?}` gives the shortest text that parses back to `f`
+            if !f.is_finite() || !same_decimal(s, &format!("{:?}", f)) {
+                return Ok(Number {
+                    n: N::Decimal(canonical(s)),
+                });
+            }
+        }
+
+        Number::from_f64(f).ok_or(ParseNumberError)
+    }
+}
+
+/// Rewrites a number as JSON has it, for the ones only lenient parsing accepts like
+/// `+.5`, `1.` or `007`.
+#[cfg(feature = "arbitrary_precision")]
+fn canonical(s: &str) -> String {
+    let unsigned = s.trim_start_matches(['-', '+']);
+    let int_len = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let int = unsigned[..int_len].trim_start_matches('0');
+
+    let mut text = String::with_capacity(s.len() + 2);
+    if s.starts_with('-') {
+        text.push('-');
+    }
+    text.push_str(if int.is_empty() { "0" } else { int });
+    let mut chars = unsigned[int_len..].chars().peekable();
+    while let Some(c) = chars.next() {
+        text.push(c);
+        if c == '.' && !chars.peek().is_some_and(char::is_ascii_digit) {
+            text.push('0');
+        }
+    }
+    text
+}
+
+/// Splits a decimal number into its significant digits and the exponent of the last digit.
+///
+/// Gives `None` if that exponent doesn't fit `i64`.
+#[cfg(feature = "arbitrary_precision")]
+fn normalize(s: &str) -> Option<(bool, String, i64)> {
+    let neg = s.starts_with('-');
+    let s = s.trim_start_matches(['-', '+']);
+    let (mantissa, exp) = match s.find(['e', 'E']) {
+        Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+        None => (s, None),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(pos) => (&mantissa[..pos], &mantissa[pos + 1..]),
+        None => (mantissa, ""),
+    };
+    let digits: String = int.chars().chain(frac.chars()).collect();
+    let trimmed = digits.trim_end_matches('0');
+    let zeros = digits.len() - trimmed.len();
+    let digits = trimmed.trim_start_matches('0').to_string();
+    // Zero has no sign or exponent to compare
+    if digits.is_empty() {
+        return Some((false, digits, 0));
+    }
+    let exp = match exp {
+        Some(exp) => exp.parse::<i64>().ok()?,
+        None => 0,
+    };
+    let exp = exp
+        .checked_sub(frac.len() as i64)?
+        .checked_add(zeros as i64)?;
+    Some((neg, digits, exp))
+}
+
+/// Checks that two decimal numbers have the same value.
+#[cfg(feature = "arbitrary_precision")]
+fn same_decimal(a: &str, b: &str) -> bool {
+    match (normalize(a), normalize(b)) {
+        (Some(a), Some(b)) => a == b,
+        // An exponent beyond `i64` can't be shifted to compare, only the same text is equal
+        _ => a == b,
+    }
+}
+
+/// Checks that `f` is exactly the value written in `s`.
+#[cfg(feature = "arbitrary_precision")]
+fn decimal_eq(s: &str, f: f64) -> bool {
+    // Every finite f64 has an exact decimal expansion shorter than 800 digits
+    same_decimal(s, &format!("{:.800e}", f))
+}
+
+macro_rules! from_unsigned {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(n: $ty) -> Self {
+                    Number { n: N::PosInt(n as u64) }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! from_signed {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(n: $ty) -> Self {
+                    let n = if n < 0 { N::NegInt(n as i64) } else { N::PosInt(n as u64) };
+                    Number { n }
+                }
+            }
+        )*
+    };
+}
+
+from_unsigned!(u8 u16 u32 u64 usize);
+from_signed!(i8 i16 i32 i64 isize);
+
+#[test]
+fn integer_precision_test() {
+    let n: Number = "16777217".parse().unwrap();
+    assert_eq!(n.as_u64(), Some(16_777_217));
+    assert_eq!(n.as_i32(), Some(16_777_217));
+
+    let n: Number = "18446744073709551615".parse().unwrap();
+    assert_eq!(n.as_u64(), Some(u64::MAX));
+    assert_eq!(n.as_i64(), None);
+    assert_eq!(n.as_f64(), None);
+
+    let n: Number = "-9223372036854775808".parse().unwrap();
+    assert_eq!(n.as_i64(), Some(i64::MIN));
+    assert_eq!(n.as_u64(), None);
+}
+
+#[test]
+fn float_test() {
+    let n: Number = "1.5e3".parse().unwrap();
+    assert!(n.is_float());
+    assert_eq!(n.as_f64(), Some(1500.0));
+    assert_eq!(n.as_i64(), None);
+
+    let n: Number = "-0".parse().unwrap();
+    assert!(n.to_f64().is_sign_negative());
+    assert_eq!(n.as_u64(), None);
+
+    assert_eq!("abc".parse::<Number>(), Err(ParseNumberError));
+}
+
+#[cfg(feature = "arbitrary_precision")]
+#[test]
+fn arbitrary_precision_test() {
+    let n: Number = "0.5".parse().unwrap();
+    assert_eq!(n.as_f64(), Some(0.5));
+
+    let n: Number = "0.1".parse().unwrap();
+    assert_eq!(n.as_f64(), Some(0.1));
+    assert!(n == Number::from_f64(0.1).unwrap());
+    assert_eq!(crate::from_str("0.1").unwrap(), crate::Value::from(0.1));
+    assert_eq!(n.to_string(), "0.1");
+    let n: Number = "1.5e-7".parse().unwrap();
+    assert_eq!(n.as_f64(), Some(1.5e-7));
+    let n: Number = "0.10000000000000000001".parse().unwrap();
+    assert_eq!(n.as_f64(), None);
+    assert_eq!(n.to_f64(), 0.1);
+    assert!(!n.value_eq(&Number::from_f64(0.1).unwrap()));
+    let n: Number = "0.1000000000000000055511151231257827021181583404541015625"
+        .parse()
+        .unwrap();
+    assert_eq!(n.as_f64(), None);
+    assert!(n.value_eq(&Number::from_f64(0.1).unwrap()));
+
+    let n: Number = "123456789012345678901234567890".parse().unwrap();
+    assert_eq!(format!("{:?}", n), "Number(123456789012345678901234567890)");
+    let m: Number = "1.2345678901234567890123456789e29".parse().unwrap();
+    assert!(n.value_eq(&m));
+    assert!(!n.value_eq(&Number::from_f64(n.to_f64()).unwrap()));
+    let n: Number = "1e400".parse().unwrap();
+    assert!(n.value_eq(&"10.0e399".parse().unwrap()));
+    let n: Number = "1e99999999999999999999".parse().unwrap();
+    assert!(!n.value_eq(&"1e0".parse().unwrap()));
+    assert!(n.value_eq(&"1e99999999999999999999".parse().unwrap()));
+    assert!(!n.value_eq(&"2e99999999999999999999".parse().unwrap()));
+    assert!(normalize("1.5e-9223372036854775808").is_none());
+
+    for (lenient, json) in [
+        (".1", "0.1"),
+        ("+0.1", "0.1"),
+        ("-.1e-400", "-0.1e-400"),
+        ("1.e400", "1.0e400"),
+        ("00012345678901234567890.", "12345678901234567890.0"),
+    ] {
+        assert_eq!(lenient.parse::<Number>().unwrap().to_string(), json);
+    }
+}
+
+#[test]
+fn json5_test() {
+    let n = Number::from_json5("0xFF").unwrap();
+    assert_eq!(n.as_u64(), Some(255));
+    let n = Number::from_json5("-0x8000000000000000").unwrap();
+    assert_eq!(n.as_i64(), Some(i64::MIN));
+    let n = Number::from_json5("-0x0").unwrap();
+    assert!(n.to_f64().is_sign_negative());
+    let n = Number::from_json5("0x10000000000000000").unwrap();
+    assert_eq!(n.as_f64(), Some(18_446_744_073_709_551_616.0));
+
+    let n = Number::from_json5("-Infinity").unwrap();
+    assert!(!n.is_finite());
+    assert_eq!(n.to_string(), "-Infinity");
+    assert!(Number::from_json5("+NaN").unwrap().to_f64().is_nan());
+    assert_eq!(Number::from_json5("+.5").unwrap().to_string(), "0.5");
+    assert_eq!(Number::from_json5("-5.e1").unwrap().to_string(), "-50.0");
+}
@@ -0,0 +1,819 @@
+//! JSON Patch (RFC 6902): applying patch documents to a [`Value`] and finding the patch
+//! between two values. JSON Merge Patch (RFC 7386) is in [`Value::merge_patch`] and
+//! [`merge_diff`].
+//!
+//! ```
+//! use json_rs_prac::json;
+//! use json_rs_prac::patch::{self, Patch};
+//!
+//! let mut doc = json!({"name": "a", "tags": ["x"]});
+//! let patch = Patch::from_value(&json!([
+//!     {"op": "replace", "path": "/name", "value": "b"},
+//!     {"op": "add", "path": "/tags/-", "value": "y"},
+//! ]))
+//! .unwrap();
+//! patch::apply(&mut doc, &patch).unwrap();
+//! assert_eq!(doc, json!({"name": "b", "tags": ["x", "y"]}));
+//!
+//! let back = patch::diff(&doc, &json!({"name": "a", "tags": ["x"]}));
+//! assert_eq!(
+//!     back.to_value(),
+//!     json!([
+//!         {"op": "replace", "path": "/name", "value": "a"},
+//!         {"op": "remove", "path": "/tags/1"},
+//!     ])
+//! );
+//! ```
+
+use std::fmt;
+
+use crate::pointer;
+use crate::{Map, PointerError, Value};
+
+/// An operation of a patch. Paths are JSON Pointers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// Inserts into an array or adds or replaces an object member, see
+    /// [`Value::pointer_insert`].
+    Add {
+        path: String,
+        value: Value,
+    },
+    Remove {
+        path: String,
+    },
+    /// Replaces a value that has to exist.
+    Replace {
+        path: String,
+        value: Value,
+    },
+    /// Removes the value at `from` and adds it at `path`.
+    Move {
+        from: String,
+        path: String,
+    },
+    /// Adds a copy of the value at `from` at `path`.
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// Fails unless the value at `path` equals `value`. Numbers are equal when their
+    /// values are, so `1` equals `1.0`, but they aren't rounded to `f64` for that:
+    /// 2^53 doesn't equal 2^53 + 1.
+    Test {
+        path: String,
+        value: Value,
+    },
+}
+
+/// A JSON Patch document, a list of operations applied one after the other.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Patch(pub Vec<Operation>);
+
+impl Patch {
+    /// Reads a patch document. Members that don't belong to an operation are ignored.
+    pub fn from_value(value: &Value) -> Result<Patch, PatchError> {
+        let ops = value.as_array().ok_or_else(|| PatchError {
+            operation: None,
+            kind: PatchErrorKind::Malformed("a patch has to be an array".into()),
+        })?;
+
+        let ops = ops.iter().enumerate().map(|(idx, op)| {
+            Operation::from_value(op).map_err(|message| PatchError {
+                operation: Some(idx),
+                kind: PatchErrorKind::Malformed(message),
+            })
+        });
+        Ok(Patch(ops.collect::<Result<_, _>>()?))
+    }
+
+    /// The patch as a JSON Patch document.
+    pub fn to_value(&self) -> Value {
+        self.0.iter().map(Operation::to_value).collect()
+    }
+}
+
+impl Operation {
+    fn from_value(value: &Value) -> Result<Operation, String> {
+        let obj = value
+            .as_object()
+            .ok_or_else(|| "an operation has to be an object".to_string())?;
+        let string = |key: &str| match obj.get(key) {
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(_) => Err(format!("{:?} has to be a string", key)),
+            None => Err(format!("missing {:?}", key)),
+        };
+        let value = || obj.get("value").cloned().ok_or("missing \"value\"");
+
+        let op = string("op")?;
+        let path = string("path")?;
+        Ok(match op.as_str() {
+            "add" => Operation::Add {
+                path,
+                value: value()?,
+            },
+            "remove" => Operation::Remove { path },
+            "replace" => Operation::Replace {
+                path,
+                value: value()?,
+            },
+            "move" => Operation::Move {
+                from: string("from")?,
+                path,
+            },
+            "copy" => Operation::Copy {
+                from: string("from")?,
+                path,
+            },
+            "test" => Operation::Test {
+                path,
+                value: value()?,
+            },
+            _ => return Err(format!("unknown operation {:?}", op)),
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let (op, path, from, value) = match self {
+            Operation::Add { path, value } => ("add", path, None, Some(value)),
+            Operation::Remove { path } => ("remove", path, None, None),
+            Operation::Replace { path, value } => ("replace", path, None, Some(value)),
+            Operation::Move { from, path } => ("move", path, Some(from), None),
+            Operation::Copy { from, path } => ("copy", path, Some(from), None),
+            Operation::Test { path, value } => ("test", path, None, Some(value)),
+        };
+
+        let mut obj = Map::new();
+        obj.insert("op".to_string(), op.into());
+        obj.insert("path".to_string(), path.as_str().into());
+        if let Some(from) = from {
+            obj.insert("from".to_string(), from.as_str().into());
+        }
+        if let Some(value) = value {
+            obj.insert("value".to_string(), value.clone());
+        }
+        Value::Object(obj)
+    }
+
+    fn apply(&self, doc: &mut Value) -> Result<(), PatchErrorKind> {
+        match self {
+            Operation::Add { path, value } => {
+                doc.pointer_insert(path, value.clone())?;
+            }
+            Operation::Remove { path } => {
+                doc.pointer_remove(path)?;
+            }
+            Operation::Replace { path, value } => *doc.pointer_get_mut(path)? = value.clone(),
+            Operation::Move { from, path } => {
+                let into_itself = path.strip_prefix(from.as_str());
+                if into_itself.is_some_and(|rest| rest.starts_with('/')) {
+                    return Err(PatchErrorKind::MoveIntoItself);
+                }
+                let value = doc.pointer_remove(from)?;
+                doc.pointer_insert(path, value)?;
+            }
+            Operation::Copy { from, path } => {
+                let value = doc.pointer_get(from)?.clone();
+                doc.pointer_insert(path, value)?;
+            }
+            Operation::Test { path, value } => {
+                if !equal(doc.pointer_get(path)?, value) {
+                    return Err(PatchErrorKind::TestFailed);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Equality as the `test` operation sees it.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.value_eq(b),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+/// What went wrong in a [`PatchError`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchErrorKind {
+    /// The patch document isn't an array of operations with the members they need.
+    Malformed(String),
+    /// A path or `from` doesn't point where the operation needs it to.
+    Pointer(PointerError),
+    /// A `test` operation found a different value.
+    TestFailed,
+    /// A `move` has a `path` inside its `from`.
+    MoveIntoItself,
+}
+
+impl From<PointerError> for PatchErrorKind {
+    fn from(e: PointerError) -> Self {
+        PatchErrorKind::Pointer(e)
+    }
+}
+
+/// Error reading or applying a patch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchError {
+    operation: Option<usize>,
+    kind: PatchErrorKind,
+}
+
+impl PatchError {
+    /// Index of the operation that failed, `None` if the patch isn't an array at all.
+    pub fn operation(&self) -> Option<usize> {
+        self.operation
+    }
+
+    pub fn kind(&self) -> &PatchErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            PatchErrorKind::Malformed(message) => f.write_str(message)?,
+            PatchErrorKind::Pointer(e) => write!(f, "{}", e)?,
+            PatchErrorKind::TestFailed => f.write_str("test failed")?,
+            PatchErrorKind::MoveIntoItself => f.write_str("cannot move a value into itself")?,
+        }
+        if let Some(idx) = self.operation {
+            write!(f, " in operation {}", idx)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Applies `patch` to `doc` as a whole: if an operation fails, `doc` is left as it was.
+pub fn apply(doc: &mut Value, patch: &Patch) -> Result<(), PatchError> {
+    // Working on a copy is what makes it all or nothing
+    let mut patched = doc.clone();
+    for (idx, op) in patch.0.iter().enumerate() {
+        op.apply(&mut patched).map_err(|kind| PatchError {
+            operation: Some(idx),
+            kind,
+        })?;
+    }
+    *doc = patched;
+    Ok(())
+}
+
+/// Finds a patch that turns `from` into `to`.
+///
+/// Objects are compared member by member. Arrays are compared with the shortest edit
+/// script between them (Myers' algorithm), so inserted and removed elements give `add`
+/// and `remove` operations, and where elements are removed and others added in the
+/// same place they are compared in turn. Anything else that changed is replaced as a
+/// whole.
+pub fn diff(from: &Value, to: &Value) -> Patch {
+    let mut ops = Vec::new();
+    diff_into(&mut ops, String::new(), from, to);
+    Patch(ops)
+}
+
+fn diff_into(ops: &mut Vec<Operation>, path: String, from: &Value, to: &Value) {
+    if from == to {
+        return;
+    }
+
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a.iter() {
+                let path = format!("{}/{}", path, pointer::escape(key));
+                match b.get(key) {
+                    Some(other) => diff_into(ops, path, value, other),
+                    None => ops.push(Operation::Remove { path }),
+                }
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                ops.push(Operation::Add {
+                    path: format!("{}/{}", path, pointer::escape(key)),
+                    value: value.clone(),
+                });
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => diff_arrays(ops, &path, a, b),
+        _ => ops.push(Operation::Replace {
+            path,
+            value: to.clone(),
+        }),
+    }
+}
+
+fn diff_arrays(ops: &mut Vec<Operation>, path: &str, a: &[Value], b: &[Value]) {
+    let mut edits = Vec::new();
+    edit_script(a, b, &mut edits);
+
+    // Elements of `a` and `b` done so far, the patched array has those of `b` in front
+    let (mut i, mut j) = (0, 0);
+    let mut edits = &edits[..];
+    while let Some((&edit, rest)) = edits.split_first() {
+        if edit == Edit::Keep {
+            i += 1;
+            j += 1;
+            edits = rest;
+            continue;
+        }
+
+        let run = edits.iter().take_while(|&&edit| edit != Edit::Keep).count();
+        let removed = edits[..run]
+            .iter()
+            .filter(|&&edit| edit == Edit::Remove)
+            .count();
+        let added = run - removed;
+        edits = &edits[run..];
+
+        let changed = removed.min(added);
+        for k in 0..changed {
+            diff_into(ops, format!("{}/{}", path, j + k), &a[i + k], &b[j + k]);
+        }
+        for k in changed..added {
+            ops.push(Operation::Add {
+                path: format!("{}/{}", path, j + k),
+                value: b[j + k].clone(),
+            });
+        }
+        for k in (changed..removed).rev() {
+            ops.push(Operation::Remove {
+                path: format!("{}/{}", path, j + k),
+            });
+        }
+        i += removed;
+        j += added;
+    }
+}
+
+/// What happens to an element on the way from one array to another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Appends a shortest edit script turning `a` into `b` to `edits`, splitting the
+/// problem at the middle snake of Myers' algorithm so it needs only linear space.
+fn edit_script(a: &[Value], b: &[Value], edits: &mut Vec<Edit>) {
+    let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    edits.extend(std::iter::repeat_n(Edit::Keep, prefix));
+    if a_mid.is_empty() || b_mid.is_empty() {
+        edits.extend(std::iter::repeat_n(Edit::Remove, a_mid.len()));
+        edits.extend(std::iter::repeat_n(Edit::Add, b_mid.len()));
+    } else {
+        let (x, y) = middle_snake(a_mid, b_mid);
+        edit_script(&a_mid[..x], &b_mid[..y], edits);
+        edit_script(&a_mid[x..], &b_mid[y..], edits);
+    }
+    edits.extend(std::iter::repeat_n(Edit::Keep, suffix));
+}
+
+/// A point on a shortest edit path from `a` to `b` that splits it into two paths of
+/// about half the length. `a` and `b` must start and end with different elements.
+fn middle_snake(a: &[Value], b: &[Value]) -> (usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let max = (n + m + 1) / 2;
+    // Furthest `x` on each diagonal `x - y`, going forward from the start and backward
+    // from the end, where the diagonals and `x` are counted from the end
+    let mut forward = vec![0; 2 * max as usize + 3];
+    let mut backward = forward.clone();
+    let at = |k: isize| (k + max + 1) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+
+            let reverse = delta - k;
+            if delta % 2 != 0 && reverse.abs() < d && x + backward[at(reverse)] >= n {
+                return (x as usize, y as usize);
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+
+            let forward_k = delta - k;
+            if delta % 2 == 0 && forward_k.abs() <= d && forward[at(forward_k)] + x >= n {
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+    unreachable!("the paths meet after at most n + m steps")
+}
+
+impl Value {
+    /// Applies a JSON Merge Patch (RFC 7386): an object in `patch` is merged into the
+    /// object here member by member, `null` removing a member, and anything else
+    /// replaces the value.
+    ///
+    /// ```
+    /// use json_rs_prac::json;
+    ///
+    /// let mut config = json!({"db": {"host": "localhost", "port": 5432}, "debug": true});
+    /// config.merge_patch(&json!({"db": {"host": "db.internal"}, "debug": null}));
+    /// assert_eq!(config, json!({"db": {"host": "db.internal", "port": 5432}}));
+    /// ```
+    pub fn merge_patch(&mut self, patch: &Value) {
+        let members = match patch {
+            Value::Object(members) => members,
+            _ => {
+                *self = patch.clone();
+                return;
+            }
+        };
+        if !self.is_object() {
+            *self = Value::Object(Map::new());
+        }
+
+        for (key, value) in members.iter() {
+            if value.is_null() {
+                self.as_object_mut().unwrap().remove(key);
+            } else {
+                self[key.as_str()].merge_patch(value);
+            }
+        }
+    }
+}
+
+/// Finds a merge patch that turns `from` into `to` with [`Value::merge_patch`].
+///
+/// Merge patches can't set anything to `null` inside an object, since `null` removes
+/// the member, so such a member of `to` ends up missing instead. Arrays are always
+/// replaced as a whole.
+pub fn merge_diff(from: &Value, to: &Value) -> Value {
+    let (from, to) = match (from, to) {
+        (Value::Object(from), Value::Object(to)) => (from, to),
+        _ => return to.clone(),
+    };
+
+    let mut patch = Map::new();
+    for (key, value) in from.iter() {
+        match to.get(key) {
+            Some(other) if other == value => {}
+            Some(other) => {
+                patch.insert(key.clone(), merge_diff(value, other));
+            }
+            None => {
+                patch.insert(key.clone(), Value::Null);
+            }
+        }
+    }
+    for (key, value) in to.iter().filter(|(key, _)| !from.contains_key(*key)) {
+        patch.insert(key.clone(), value.clone());
+    }
+    Value::Object(patch)
+}
+
+#[test]
+fn apply_test() {
+    // Examples from RFC 6902 appendix A
+    let cases = vec![
+        (
+            json!({"foo": "bar"}),
+            json!([{"op": "add", "path": "/baz", "value": "qux"}]),
+            json!({"baz": "qux", "foo": "bar"}),
+        ),
+        (
+            json!({"foo": ["bar", "baz"]}),
+            json!([{"op": "add", "path": "/foo/1", "value": "qux"}]),
+            json!({"foo": ["bar", "qux", "baz"]}),
+        ),
+        (
+            json!({"baz": "qux", "foo": "bar"}),
+            json!([{"op": "remove", "path": "/baz"}]),
+            json!({"foo": "bar"}),
+        ),
+        (
+            json!({"baz": "qux", "foo": "bar"}),
+            json!([{"op": "replace", "path": "/baz", "value": "boo"}]),
+            json!({"baz": "boo", "foo": "bar"}),
+        ),
+        (
+            json!({"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}),
+            json!([{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]),
+            json!({"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}),
+        ),
+        (
+            json!({"foo": ["all", "grass", "cows", "eat"]}),
+            json!([{"op": "move", "from": "/foo/1", "path": "/foo/3"}]),
+            json!({"foo": ["all", "cows", "eat", "grass"]}),
+        ),
+        (
+            json!({"baz": "value", "foo": ["a", 2, "c"]}),
+            json!([
+                {"op": "test", "path": "/baz", "value": "value"},
+                {"op": "test", "path": "/foo/1", "value": 2},
+            ]),
+            json!({"baz": "value", "foo": ["a", 2, "c"]}),
+        ),
+        (
+            json!({"foo": "bar"}),
+            json!([{"op": "add", "path": "/child", "value": {"grandchild": {}}}]),
+            json!({"foo": "bar", "child": {"grandchild": {}}}),
+        ),
+        (
+            json!({"foo": "bar"}),
+            json!([{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]),
+            json!({"foo": "bar", "baz": "qux"}),
+        ),
+        (
+            json!({"foo": ["bar"]}),
+            json!([{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]),
+            json!({"foo": ["bar", ["abc", "def"]]}),
+        ),
+        (
+            json!({"/": 9, "~1": 10}),
+            json!([{"op": "test", "path": "/~01", "value": 10}]),
+            json!({"/": 9, "~1": 10}),
+        ),
+        (
+            json!({"a": 1}),
+            json!([{"op": "copy", "from": "", "path": "/b"}]),
+            json!({"a": 1, "b": {"a": 1}}),
+        ),
+    ];
+
+    for (mut doc, patch, expected) in cases {
+        let patch = Patch::from_value(&patch).unwrap();
+        apply(&mut doc, &patch).unwrap();
+        assert_eq!(doc, expected, "{:?}", patch);
+    }
+}
+
+#[test]
+fn apply_error_test() {
+    let original = json!({"foo": "bar", "list": [1]});
+    let errors = vec![
+        (
+            json!([{"op": "add", "path": "/a", "value": 1}, {"op": "remove", "path": "/baz"}]),
+            "no such key at \"/baz\" in operation 1",
+        ),
+        (
+            json!([{"op": "remove", "path": "/foo"}, {"op": "test", "path": "/list/0", "value": "1"}]),
+            "test failed in operation 1",
+        ),
+        (
+            json!([{"op": "add", "path": "/baz/bat", "value": "qux"}]),
+            "no such key at \"/baz\" in operation 0",
+        ),
+        (
+            json!([{"op": "add", "path": "/list/2", "value": 2}]),
+            "index out of bounds at \"/list/2\", the array has 1 elements in operation 0",
+        ),
+        (
+            json!([{"op": "move", "from": "/list", "path": "/list/0"}]),
+            "cannot move a value into itself in operation 0",
+        ),
+        (
+            json!([{"op": "replace", "path": "/x", "value": 1}]),
+            "no such key at \"/x\" in operation 0",
+        ),
+    ];
+    for (patch, message) in errors {
+        let mut doc = original.clone();
+        let patch = Patch::from_value(&patch).unwrap();
+        assert_eq!(apply(&mut doc, &patch).unwrap_err().to_string(), message);
+        assert_eq!(doc, original);
+    }
+
+    let malformed = vec![
+        (json!({}), "a patch has to be an array"),
+        (
+            json!([1]),
+            "an operation has to be an object in operation 0",
+        ),
+        (
+            json!([{"op": "add", "path": "/a", "value": 1}, {"op": "add", "path": "/a"}]),
+            "missing \"value\" in operation 1",
+        ),
+        (
+            json!([{"op": "copy", "path": "/a"}]),
+            "missing \"from\" in operation 0",
+        ),
+        (
+            json!([{"op": "foo", "path": "/a"}]),
+            "unknown operation \"foo\" in operation 0",
+        ),
+        (
+            json!([{"op": "remove", "path": 1}]),
+            "\"path\" has to be a string in operation 0",
+        ),
+    ];
+    for (patch, message) in malformed {
+        assert_eq!(Patch::from_value(&patch).unwrap_err().to_string(), message);
+    }
+
+    let test = |doc: Value, value: Value| {
+        let patch = json!([{"op": "test", "path": "/0", "value": value}]);
+        apply(&mut json!([doc]), &Patch::from_value(&patch).unwrap()).is_ok()
+    };
+    assert!(test(json!({"a": 1}), json!({"a": 1.0})));
+    assert!(test(json!([-3.0, 0]), json!([-3, -0.0])));
+    assert!(!test(json!(1), json!(1.5)));
+    assert!(!test(json!(-1), json!(1.0)));
+    // 2^53 and 2^53 + 1 are the same `f64`, but not the same number
+    assert!(!test(
+        json!(9_007_199_254_740_992.0),
+        json!(9_007_199_254_740_993u64)
+    ));
+    assert!(!test(
+        json!(9_007_199_254_740_992u64),
+        json!(9_007_199_254_740_993u64)
+    ));
+    assert!(test(json!(u64::MAX), json!(u64::MAX)));
+    assert!(!test(json!(18_446_744_073_709_551_616.0), json!(u64::MAX)));
+    assert!(test(json!(-9_223_372_036_854_775_808.0), json!(i64::MIN)));
+}
+
+#[test]
+fn diff_test() {
+    let pairs = vec![
+        (json!(1), json!(1)),
+        (json!(1), json!(1.0)),
+        (json!({"a": 1}), json!([1])),
+        (
+            json!({"a": {"b": [1, 2, 3], "c/~": null}, "d": true}),
+            json!({"a": {"b": [0, 1, 3, 4], "e": "x"}, "d": true}),
+        ),
+        (json!([1, 2, 3, 4, 5]), json!([1, 5])),
+        (json!([1, 2, 3]), json!([0, 1, 2, 3])),
+        (json!([[1], [2]]), json!([[1, 2], [2], 3])),
+        (json!([1, 2, 3, 4, 5]), json!([1, 2, 9, 3, 4, 5, 6])),
+        (json!([1, 2, 3, 4, 5, 6]), json!([2, 7, 3, 5, 8, 6, 1])),
+        (json!(["a", "b", "c"]), json!(["x", "y"])),
+        (json!([]), json!([1, 2])),
+        (
+            json!([9_007_199_254_740_992u64]),
+            json!([9_007_199_254_740_993u64]),
+        ),
+    ];
+    for (from, to) in pairs {
+        let patch = diff(&from, &to);
+        let mut doc = from.clone();
+        apply(&mut doc, &patch).unwrap();
+        assert_eq!(doc, to, "{:?}", patch);
+        assert_eq!(Patch::from_value(&patch.to_value()).unwrap(), patch);
+    }
+
+    let patch = diff(&json!([1, 2, 3]), &json!([0, 1, 2, 3]));
+    assert_eq!(
+        patch.to_value(),
+        json!([{"op": "add", "path": "/0", "value": 0}])
+    );
+    let patch = diff(&json!([1, 2, 3, 4, 5]), &json!([1, 5]));
+    assert_eq!(
+        patch.to_value(),
+        json!([
+            {"op": "remove", "path": "/3"},
+            {"op": "remove", "path": "/2"},
+            {"op": "remove", "path": "/1"},
+        ])
+    );
+    let patch = diff(&json!([1, 2, 3, 4, 5]), &json!([1, 2, 9, 3, 4, 5, 6]));
+    assert_eq!(
+        patch.to_value(),
+        json!([
+            {"op": "add", "path": "/2", "value": 9},
+            {"op": "add", "path": "/6", "value": 6},
+        ])
+    );
+    let patch = diff(&json!([0, 1, 2, 3]), &json!([1, 2, [3]]));
+    assert_eq!(
+        patch.to_value(),
+        json!([
+            {"op": "remove", "path": "/0"},
+            {"op": "replace", "path": "/2", "value": [3]},
+        ])
+    );
+    let patch = diff(&json!({"a": 1, "b": 2}), &json!({"b": 3, "c/~": 4}));
+    assert_eq!(
+        patch.to_value(),
+        json!([
+            {"op": "remove", "path": "/a"},
+            {"op": "replace", "path": "/b", "value": 3},
+            {"op": "add", "path": "/c~1~0", "value": 4},
+        ])
+    );
+}
+
+#[test]
+fn merge_patch_test() {
+    // Examples from RFC 7386 appendix A
+    let cases = vec![
+        (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+        (
+            json!({"a": "b"}),
+            json!({"b": "c"}),
+            json!({"a": "b", "b": "c"}),
+        ),
+        (json!({"a": "b"}), json!({"a": null}), json!({})),
+        (
+            json!({"a": "b", "b": "c"}),
+            json!({"a": null}),
+            json!({"b": "c"}),
+        ),
+        (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+        (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+        (
+            json!({"a": {"b": "c"}}),
+            json!({"a": {"b": "d", "c": null}}),
+            json!({"a": {"b": "d"}}),
+        ),
+        (
+            json!({"a": [{"b": "c"}]}),
+            json!({"a": [1]}),
+            json!({"a": [1]}),
+        ),
+        (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+        (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+        (json!({"a": "foo"}), json!(null), json!(null)),
+        (json!({"a": "foo"}), json!("bar"), json!("bar")),
+        (
+            json!({"e": null}),
+            json!({"a": 1}),
+            json!({"e": null, "a": 1}),
+        ),
+        (
+            json!([1, 2]),
+            json!({"a": "b", "c": null}),
+            json!({"a": "b"}),
+        ),
+        (
+            json!({}),
+            json!({"a": {"bb": {"ccc": null}}}),
+            json!({"a": {"bb": {}}}),
+        ),
+    ];
+
+    for (mut target, patch, expected) in cases {
+        target.merge_patch(&patch);
+        assert_eq!(target, expected, "{}", patch);
+    }
+}
+
+#[test]
+fn merge_diff_test() {
+    let pairs = vec![
+        (json!(1), json!(1)),
+        (json!({"a": 1}), json!({"a": 1})),
+        (json!({"a": 1}), json!([1])),
+        (json!([1]), json!({})),
+        (
+            json!({"a": {"b": [1, 2], "c": "x", "d": {"e": 1}}, "f": 1}),
+            json!({"a": {"b": [1], "c": "x", "d": 2}, "g": {"h": [null]}}),
+        ),
+    ];
+    for (from, to) in pairs {
+        let patch = merge_diff(&from, &to);
+        let mut doc = from.clone();
+        doc.merge_patch(&patch);
+        assert_eq!(doc, to, "{}", patch);
+    }
+
+    assert_eq!(
+        merge_diff(
+            &json!({"a": 1, "b": {"c": 2, "d": 3}}),
+            &json!({"b": {"c": 2, "d": 4}})
+        ),
+        json!({"a": null, "b": {"d": 4}})
+    );
+    assert_eq!(merge_diff(&json!({"a": 1}), &json!({"a": 1})), json!({}));
+}
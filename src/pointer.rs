@@ -0,0 +1,400 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::mem;
+
+use crate::Value;
+
+/// What went wrong in a [`PointerError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerErrorKind {
+    /// The pointer is neither empty nor starts with `/`, or has a `~` that isn't
+    /// followed by `0` or `1`.
+    Syntax,
+    /// An object doesn't have the key.
+    NoSuchKey,
+    /// An array was indexed with something other than a number without leading zeros,
+    /// or `-` where no new element can go.
+    InvalidIndex,
+    /// An array index is past the end of the array.
+    IndexOutOfBounds { len: usize },
+    /// A value on the way is neither an array nor an object.
+    NotContainer,
+}
+
+/// Error returned when a JSON Pointer can't be followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerError {
+    /// The pointer up to and including the segment that failed.
+    pointer: String,
+    kind: PointerErrorKind,
+}
+
+impl PointerError {
+    fn new(pointer: &str, kind: PointerErrorKind) -> Self {
+        PointerError {
+            pointer: pointer.to_string(),
+            kind,
+        }
+    }
+
+    /// The part of the pointer up to and including the segment that failed, or all of
+    /// it for [`PointerErrorKind::Syntax`].
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    pub fn kind(&self) -> &PointerErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            PointerErrorKind::Syntax => write!(f, "invalid JSON Pointer {:?}", self.pointer),
+            PointerErrorKind::NoSuchKey => write!(f, "no such key at {:?}", self.pointer),
+            PointerErrorKind::InvalidIndex => {
+                write!(f, "invalid array index at {:?}", self.pointer)
+            }
+            PointerErrorKind::IndexOutOfBounds { len } => write!(
+                f,
+                "index out of bounds at {:?}, the array has {} elements",
+                self.pointer, len
+            ),
+            PointerErrorKind::NotContainer => {
+                write!(f, "no array or object to index at {:?}", self.pointer)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// Escapes a key for use in a JSON Pointer.
+pub(crate) fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// A segment of a pointer, unescaped.
+struct Token<'a> {
+    /// Where the segment ends in the pointer.
+    end: usize,
+    key: Cow<'a, str>,
+}
+
+impl Token<'_> {
+    fn error(&self, pointer: &str, kind: PointerErrorKind) -> PointerError {
+        PointerError::new(&pointer[..self.end], kind)
+    }
+
+    /// The array index the segment names, `len` for `-`.
+    fn index(&self, pointer: &str, len: usize) -> Result<usize, PointerError> {
+        let key = &*self.key;
+        if key == "-" {
+            return Ok(len);
+        }
+        let digits = !key.is_empty() && key.bytes().all(|b| b.is_ascii_digit());
+        if !digits || (key.len() > 1 && key.starts_with('0')) {
+            return Err(self.error(pointer, PointerErrorKind::InvalidIndex));
+        }
+        // Too long to fit is out of bounds too
+        Ok(key.parse().unwrap_or(usize::MAX))
+    }
+
+    /// The index of an existing element.
+    fn element(&self, pointer: &str, len: usize) -> Result<usize, PointerError> {
+        match self.index(pointer, len)? {
+            idx if idx < len => Ok(idx),
+            _ if &*self.key == "-" => Err(self.error(pointer, PointerErrorKind::InvalidIndex)),
+            _ => Err(self.error(pointer, PointerErrorKind::IndexOutOfBounds { len })),
+        }
+    }
+}
+
+fn parse(pointer: &str) -> Result<Vec<Token<'_>>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let syntax = || PointerError::new(pointer, PointerErrorKind::Syntax);
+    let rest = pointer.strip_prefix('/').ok_or_else(syntax)?;
+
+    let mut end = 0;
+    rest.split('/')
+        .map(|segment| {
+            end += 1 + segment.len();
+            if !segment.contains('~') {
+                return Ok(Token {
+                    end,
+                    key: Cow::Borrowed(segment),
+                });
+            }
+
+            let mut key = String::with_capacity(segment.len());
+            let mut chars = segment.chars();
+            while let Some(c) = chars.next() {
+                key.push(match c {
+                    '~' => match chars.next() {
+                        Some('0') => '~',
+                        Some('1') => '/',
+                        _ => return Err(syntax()),
+                    },
+                    c => c,
+                });
+            }
+            Ok(Token {
+                end,
+                key: Cow::Owned(key),
+            })
+        })
+        .collect()
+}
+
+fn resolve<'v>(
+    mut value: &'v Value,
+    pointer: &str,
+    tokens: &[Token],
+) -> Result<&'v Value, PointerError> {
+    for token in tokens {
+        value = match value {
+            Value::Object(obj) => obj
+                .get(&*token.key)
+                .ok_or_else(|| token.error(pointer, PointerErrorKind::NoSuchKey))?,
+            Value::Array(arr) => &arr[token.element(pointer, arr.len())?],
+            _ => return Err(token.error(pointer, PointerErrorKind::NotContainer)),
+        };
+    }
+    Ok(value)
+}
+
+fn resolve_mut<'v>(
+    mut value: &'v mut Value,
+    pointer: &str,
+    tokens: &[Token],
+) -> Result<&'v mut Value, PointerError> {
+    for token in tokens {
+        value = match value {
+            Value::Object(obj) => obj
+                .get_mut(&*token.key)
+                .ok_or_else(|| token.error(pointer, PointerErrorKind::NoSuchKey))?,
+            Value::Array(arr) => {
+                let idx = token.element(pointer, arr.len())?;
+                &mut arr[idx]
+            }
+            _ => return Err(token.error(pointer, PointerErrorKind::NotContainer)),
+        };
+    }
+    Ok(value)
+}
+
+/// JSON Pointer (RFC 6901) access. A pointer is either empty, for the value itself, or
+/// a `/` followed by each key or array index on the way, with `~` written as `~0` and
+/// `/` as `~1` in keys.
+///
+/// ```
+/// use json_rs_prac::json;
+///
+/// let mut value = json!({"a/b": [1, {"~": 2}]});
+/// assert_eq!(value.pointer("/a~1b/1/~0"), Some(&json!(2)));
+///
+/// value.pointer_insert("/a~1b/-", json!(3)).unwrap();
+/// assert_eq!(value.pointer_remove("/a~1b/0").unwrap(), json!(1));
+/// assert_eq!(value.to_string(), r#"{"a/b":[{"~":2},3]}"#);
+///
+/// let err = value.pointer_remove("/a~1b/x").unwrap_err();
+/// assert_eq!(err.to_string(), "invalid array index at \"/a~1b/x\"");
+/// let err = value.pointer_get("/a~1b/0/x/y").unwrap_err();
+/// assert_eq!(err.pointer(), "/a~1b/0/x");
+/// ```
+impl Value {
+    /// The value `pointer` points at, if there is one.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        self.pointer_get(pointer).ok()
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        self.pointer_get_mut(pointer).ok()
+    }
+
+    /// Like [`pointer`](Value::pointer), saying which segment couldn't be followed if
+    /// there is no such value.
+    pub fn pointer_get(&self, pointer: &str) -> Result<&Value, PointerError> {
+        resolve(self, pointer, &parse(pointer)?)
+    }
+
+    pub fn pointer_get_mut(&mut self, pointer: &str) -> Result<&mut Value, PointerError> {
+        resolve_mut(self, pointer, &parse(pointer)?)
+    }
+
+    /// Puts `value` where `pointer` points, like the `add` operation of JSON Patch.
+    ///
+    /// An object member is added or replaced, returning the old value. In an array
+    /// `value` is inserted before the element at the index, which may be one past the
+    /// end or `-` to append. The empty pointer replaces the whole value.
+    pub fn pointer_insert(
+        &mut self,
+        pointer: &str,
+        value: Value,
+    ) -> Result<Option<Value>, PointerError> {
+        let tokens = parse(pointer)?;
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => return Ok(Some(mem::replace(self, value))),
+        };
+
+        match resolve_mut(self, pointer, parents)? {
+            Value::Object(obj) => Ok(obj.insert(last.key.to_string(), value)),
+            Value::Array(arr) => match last.index(pointer, arr.len())? {
+                idx if idx <= arr.len() => {
+                    arr.insert(idx, value);
+                    Ok(None)
+                }
+                _ => Err(last.error(
+                    pointer,
+                    PointerErrorKind::IndexOutOfBounds { len: arr.len() },
+                )),
+            },
+            _ => Err(last.error(pointer, PointerErrorKind::NotContainer)),
+        }
+    }
+
+    /// Takes out the value `pointer` points at, shifting later array elements down.
+    /// The empty pointer takes the whole value, leaving `null`.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<Value, PointerError> {
+        let tokens = parse(pointer)?;
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => return Ok(self.take()),
+        };
+
+        match resolve_mut(self, pointer, parents)? {
+            Value::Object(obj) => obj
+                .remove(&*last.key)
+                .ok_or_else(|| last.error(pointer, PointerErrorKind::NoSuchKey)),
+            Value::Array(arr) => Ok(arr.remove(last.element(pointer, arr.len())?)),
+            _ => Err(last.error(pointer, PointerErrorKind::NotContainer)),
+        }
+    }
+}
+
+#[test]
+fn pointer_test() {
+    let value = crate::from_str(include_str!("../example.json")).unwrap();
+    let entry = "/glossary/GlossDiv/GlossList/GlossEntry";
+
+    assert_eq!(
+        value.pointer(&format!("{}/ID", entry)),
+        Some(&json!("SGML"))
+    );
+    assert_eq!(
+        value.pointer(&format!("{}/GlossDef/GlossSeeAlso/1", entry)),
+        Some(&json!("XML"))
+    );
+    assert_eq!(value.pointer(""), Some(&value));
+    for missing in &[
+        "glossary",
+        "/glossary/x",
+        "/glossary/title/0",
+        "/glossary/GlossDiv/GlossList/GlossEntry/GlossDef/GlossSeeAlso/2",
+        "/glossary/GlossDiv/GlossList/GlossEntry/GlossDef/GlossSeeAlso/-",
+        "/glossary/GlossDiv/GlossList/GlossEntry/GlossDef/GlossSeeAlso/01",
+    ] {
+        assert_eq!(value.pointer(missing), None, "{}", missing);
+    }
+
+    let value = json!({"": 0, "a/b": 1, "m~n": 2, "~1": 3, " ": 4, "c%d": 5});
+    for (pointer, expected) in &[
+        ("/", 0),
+        ("/a~1b", 1),
+        ("/m~0n", 2),
+        ("/~01", 3),
+        ("/ ", 4),
+        ("/c%d", 5),
+    ] {
+        assert_eq!(
+            value.pointer(pointer),
+            Some(&json!(*expected)),
+            "{}",
+            pointer
+        );
+    }
+    assert_eq!(value.pointer("/m~2n"), None);
+    assert_eq!(value.pointer("/m~"), None);
+
+    for (pointer, failed, kind) in [
+        ("/a~1b/0", "/a~1b/0", PointerErrorKind::NotContainer),
+        ("/x/0", "/x", PointerErrorKind::NoSuchKey),
+        ("x", "x", PointerErrorKind::Syntax),
+    ] {
+        let err = value.pointer_get(pointer).unwrap_err();
+        assert_eq!((err.pointer(), err.kind()), (failed, &kind));
+    }
+    let list = json!([[1]]);
+    let err = list.pointer_get("/0/1").unwrap_err();
+    assert_eq!(err.kind(), &PointerErrorKind::IndexOutOfBounds { len: 1 });
+}
+
+#[test]
+fn pointer_mut_test() {
+    let mut value = json!({"a": [1, {"b": null}]});
+
+    *value.pointer_mut("/a/1/b").unwrap() = json!(true);
+    assert_eq!(
+        value.pointer_get_mut("/a/2").unwrap_err().to_string(),
+        "index out of bounds at \"/a/2\", the array has 2 elements"
+    );
+    assert_eq!(
+        value.pointer_insert("/a/1/b", json!(2)),
+        Ok(Some(json!(true)))
+    );
+    assert_eq!(value.pointer_insert("/a/0", json!(0)), Ok(None));
+    assert_eq!(value.pointer_insert("/a/3", json!(3)), Ok(None));
+    assert_eq!(value.pointer_insert("/a/-", json!(4)), Ok(None));
+    assert_eq!(value.pointer_insert("/c", json!([])), Ok(None));
+    assert_eq!(value, json!({"a": [0, 1, {"b": 2}, 3, 4], "c": []}));
+
+    assert_eq!(value.pointer_remove("/a/2/b"), Ok(json!(2)));
+    assert_eq!(value.pointer_remove("/a/0"), Ok(json!(0)));
+    assert_eq!(value.pointer_remove("/c"), Ok(json!([])));
+    assert_eq!(value, json!({"a": [1, {}, 3, 4]}));
+
+    let errors = [
+        (
+            value.pointer_insert("/a/5", json!(0)),
+            "index out of bounds at \"/a/5\", the array has 4 elements",
+        ),
+        (
+            value.pointer_insert("/x/y", json!(0)),
+            "no such key at \"/x\"",
+        ),
+        (
+            value.pointer_insert("/a/0/z", json!(0)),
+            "no array or object to index at \"/a/0/z\"",
+        ),
+        (
+            value.pointer_insert("a", json!(0)),
+            "invalid JSON Pointer \"a\"",
+        ),
+        (
+            value.pointer_remove("/a/-").map(Some),
+            "invalid array index at \"/a/-\"",
+        ),
+        (
+            value.pointer_remove("/a/1/q").map(Some),
+            "no such key at \"/a/1/q\"",
+        ),
+        (
+            value.pointer_remove("/a/~2/q").map(Some),
+            "invalid JSON Pointer \"/a/~2/q\"",
+        ),
+    ];
+    for (res, message) in errors.iter() {
+        assert_eq!(res.as_ref().unwrap_err().to_string(), *message);
+    }
+
+    assert_eq!(
+        value.pointer_insert("", json!(1)),
+        Ok(Some(json!({"a": [1, {}, 3, 4]})))
+    );
+    assert_eq!(value.pointer_remove(""), Ok(json!(1)));
+    assert_eq!(value, Value::Null);
+}
@@ -0,0 +1,810 @@
+//! Pull parser that walks a document as a stream of [`Event`]s.
+//!
+//! It only keeps one entry per open array or object around, so documents of any size
+//! can be processed without building a [`Value`](crate::Value):
+//!
+//! ```
+//! use json_rs_prac::pull::{Event, Parser};
+//!
+//! let mut names = Vec::new();
+//! for event in Parser::new(r#"[{"name": "a"}, {"name": "b"}]"#) {
+//!     let (_position, event) = event.unwrap();
+//!     if let Event::String(s) = event {
+//!         names.push(s);
+//!     }
+//! }
+//! assert_eq!(names, ["a", "b"]);
+//! ```
+
+use std::borrow::Cow;
+use std::cmp;
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::char;
+use nom::combinator::map;
+
+use crate::error::{RawError, RawKind};
+use crate::{
+    check_input_len, drop_iteratively, expect, is_identifier_part, is_identifier_start,
+    js_comment_from, js_cow_string, js_key, js_number, key_label, skip_spaces, DuplicateKeys, Map,
+    Number, PResult, ParseError, ParseOptions, Position, Tree,
+};
+
+/// A piece of a JSON document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// Key of the next object member, its value follows.
+    Key(Cow<'a, str>),
+    String(Cow<'a, str>),
+    Number(Number),
+    Boolean(bool),
+    Null,
+}
+
+impl Event<'_> {
+    /// Copies borrowed strings so the event no longer refers to the input.
+    pub fn into_owned(self) -> Event<'static> {
+        match self {
+            Event::StartObject => Event::StartObject,
+            Event::EndObject => Event::EndObject,
+            Event::StartArray => Event::StartArray,
+            Event::EndArray => Event::EndArray,
+            Event::Key(k) => Event::Key(Cow::Owned(k.into_owned())),
+            Event::String(s) => Event::String(Cow::Owned(s.into_owned())),
+            Event::Number(n) => Event::Number(n),
+            Event::Boolean(b) => Event::Boolean(b),
+            Event::Null => Event::Null,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Array,
+    Object,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Expecting any value.
+    Value,
+    /// Right after `[`, or a comma if trailing commas are allowed, expecting a value or `]`.
+    ArrayStart,
+    /// Right after `{`, or a comma if trailing commas are allowed, expecting a key or `}`.
+    ObjectStart,
+    /// Expecting `"key":`.
+    Key,
+    /// A value ended, expecting `,`, the end of its container or the end of input.
+    AfterValue,
+    /// The document ended or an error was returned.
+    Done,
+}
+
+/// Where a parser is in the document, kept apart from the input so the push parser
+/// can carry it over from one chunk to the next.
+#[derive(Debug, Clone)]
+pub(crate) struct Machine {
+    position: Position,
+    stack: Vec<Container>,
+    /// Items so far in each container of `stack`.
+    items: Vec<usize>,
+    /// Values so far in the document.
+    nodes: usize,
+    state: State,
+    /// Whether more values may follow the first one, as in concatenated JSON.
+    stream: bool,
+    scan: Scan,
+}
+
+/// How far the search for the end of the next token got, so that partial input isn't
+/// searched from the start again each time more of it arrives.
+#[derive(Debug, Clone, Copy, Default)]
+struct Scan {
+    /// Where to go on in the rest of the input.
+    from: usize,
+    /// Length of the key, once only the `:` after it is missing.
+    key_len: Option<usize>,
+    /// Start of a comment after the key that is cut off at `from`.
+    comment: Option<usize>,
+}
+
+impl Machine {
+    pub(crate) fn new() -> Self {
+        Machine {
+            position: Position::START,
+            stack: Vec::new(),
+            items: Vec::new(),
+            nodes: 0,
+            state: State::Value,
+            stream: false,
+            scan: Scan::default(),
+        }
+    }
+
+    /// A machine for any number of values one after the other, each counted as a
+    /// document of its own for the limits.
+    pub(crate) fn stream() -> Self {
+        Machine {
+            stream: true,
+            ..Machine::new()
+        }
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Ends the document early, after an error.
+    pub(crate) fn stop(&mut self) {
+        self.state = State::Done;
+    }
+}
+
+/// Outcome of a single [`Parser::step`].
+pub(crate) enum Step<'a> {
+    Event(Position, Event<'a>),
+    /// The input ends within the next token, only returned for partial input.
+    NeedMore,
+    End,
+}
+
+/// Iterator over the [`Event`]s of a single JSON value, each with the position where
+/// it starts.
+///
+/// Accepts the same input as [`ParseOptions::parse_str`], except that
+/// [`duplicate_keys`](ParseOptions::duplicate_keys) doesn't apply, every key is
+/// reported as it appears. After an error the iterator ends.
+pub struct Parser<'a> {
+    input: &'a str,
+    rest: &'a str,
+    opts: ParseOptions,
+    /// Position of the start of `input`.
+    base: Position,
+    /// More text may follow `input`, so a token running up to its end isn't complete.
+    partial: bool,
+    m: Machine,
+    /// Where the last key ends, the position after a key event is past the `:`.
+    key_end: Position,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Parser::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, opts: ParseOptions) -> Self {
+        Parser::resume(input, opts, Machine::new(), false)
+    }
+
+    /// Continues parsing where `m` stopped, `input` being the text after that point.
+    pub(crate) fn resume(input: &'a str, opts: ParseOptions, m: Machine, partial: bool) -> Self {
+        Parser {
+            input,
+            rest: input,
+            opts,
+            base: m.position,
+            partial,
+            key_end: m.position,
+            m,
+        }
+    }
+
+    pub(crate) fn into_machine(self) -> Machine {
+        self.m
+    }
+
+    /// Bytes of the input that were parsed so far.
+    pub(crate) fn consumed(&self) -> usize {
+        self.input.len() - self.rest.len()
+    }
+
+    /// Where the parser currently is, i.e. right after the last event.
+    pub fn position(&self) -> Position {
+        self.m.position
+    }
+
+    /// Where the key of the last [`Event::Key`] ends.
+    pub(crate) fn key_end(&self) -> Position {
+        self.key_end
+    }
+
+    /// Number of arrays and objects that are currently open.
+    pub fn depth(&self) -> usize {
+        self.m.stack.len()
+    }
+
+    pub(crate) fn error(&self, e: RawError) -> ParseError {
+        ParseError::with_base(self.input, e, self.base)
+    }
+
+    /// Error for a [`Builder`] that failed on the key at `pos`.
+    pub(crate) fn key_error(&self, pos: Position, kind: RawKind<'a>) -> ParseError {
+        let mut e = RawError::new(&self.input[pos.offset - self.base.offset..], kind);
+        e.context.push("object");
+        self.error(e)
+    }
+
+    fn advance(&mut self, rest: &'a str) {
+        let consumed = &self.rest[..self.rest.len() - rest.len()];
+        if !consumed.is_empty() {
+            self.m.position = self.m.position.advance(consumed);
+            self.m.scan = Scan::default();
+        }
+        self.rest = rest;
+    }
+
+    fn parse<O>(&mut self, f: impl Fn(&'a str) -> PResult<'a, O>) -> Result<O, RawError<'a>> {
+        match f(self.rest) {
+            Ok((rest, o)) => {
+                self.advance(rest);
+                Ok(o)
+            }
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(self.with_context(e)),
+            Err(nom::Err::Incomplete(_)) => Err(RawError::expected(self.rest, "more input")),
+        }
+    }
+
+    /// Adds the same contexts the recursive parser would.
+    fn with_context(&self, mut e: RawError<'a>) -> RawError<'a> {
+        match (self.m.stack.last(), self.m.state) {
+            (Some(Container::Array), _) => e.context.push("array"),
+            (Some(Container::Object), State::Key) | (Some(Container::Object), State::Value) => {
+                e.context.extend(&["object item", "object"])
+            }
+            (Some(Container::Object), _) => e.context.push("object"),
+            (None, _) => {}
+        }
+        e
+    }
+
+    /// Skips whitespace and comments, except for a comment that may go on past the end
+    /// of partial input.
+    fn skip_spaces(&mut self) -> Result<(), RawError<'a>> {
+        if self.m.position.offset == 0 {
+            let rest = self.opts.skip_bom(self.rest);
+            self.advance(rest);
+        }
+        loop {
+            let rest = skip_spaces(self.rest, &self.opts);
+            self.advance(rest);
+            if !self.opts.allow_comments {
+                return Ok(());
+            }
+            match js_comment_from(self.rest, self.m.scan.from) {
+                Some((len, false)) if self.partial => {
+                    self.m.scan.from = len;
+                    return Ok(());
+                }
+                Some((len, false)) if self.rest.starts_with("/*") => {
+                    let e = RawError::expected(&self.rest[len..], "'*/'");
+                    return Err(self.with_context(e));
+                }
+                Some((len, _)) => self.advance(&self.rest[len..]),
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Whether the rest of the input holds the whole next token, or enough of it to
+    /// tell that it is wrong. Only needed for partial input.
+    ///
+    /// Where the search got to is kept in the machine, so that each part of the input
+    /// is only searched once however small the chunks it arrives in.
+    fn token_ready(&mut self) -> bool {
+        let first = match self.rest.chars().next() {
+            Some(c) => c,
+            None => return false,
+        };
+        // `skip_spaces` stopped at a comment that is cut off
+        if self.opts.allow_comments
+            && (self.rest == "/"
+                || matches!(
+                    js_comment_from(self.rest, self.m.scan.from),
+                    Some((_, false))
+                ))
+        {
+            return false;
+        }
+
+        let scan = self.m.scan;
+        let (rest, from) = (self.rest, scan.from);
+        let string = first == '"' || (self.opts.json5 && first == '\'');
+        match self.m.state {
+            State::AfterValue | State::Done => true,
+            State::ObjectStart | State::Key => {
+                let len = match scan.key_len {
+                    Some(len) => Ok(len),
+                    None if string => string_len(rest, from),
+                    // Identifiers end at the first character that can't be part of one
+                    None if self.opts.json5 && (is_identifier_start(first) || first == '\\') => {
+                        match rest[from..].find(|c| !is_identifier_part(c) && c != '\\') {
+                            Some(at) => Ok(from + at),
+                            None => Err(rest.len()),
+                        }
+                    }
+                    None => return true,
+                };
+                match len {
+                    // The `:` has to be there as well
+                    Ok(len) => {
+                        self.m.scan.key_len = Some(len);
+                        self.colon_ready(len)
+                    }
+                    Err(from) => {
+                        self.m.scan.from = from;
+                        false
+                    }
+                }
+            }
+            State::Value | State::ArrayStart => match first {
+                '[' | ']' | '{' => true,
+                _ if string => match string_len(rest, from) {
+                    Ok(_) => true,
+                    Err(from) => {
+                        self.m.scan.from = from;
+                        false
+                    }
+                },
+                // Literals and numbers end at the next delimiter
+                _ if rest[from..].contains(|c| " \n\r\t,:[]{}\"'/".contains(c)) => true,
+                _ => {
+                    self.m.scan.from = rest.len();
+                    false
+                }
+            },
+        }
+    }
+
+    /// Whether the whitespace and comments after a key of `len` bytes are followed by
+    /// something, which should be the `:`.
+    fn colon_ready(&mut self, len: usize) -> bool {
+        let rest = self.rest;
+        let mut at = cmp::max(len, self.m.scan.from);
+        let mut comment = self.m.scan.comment.take();
+        loop {
+            let start = match comment.take() {
+                Some(start) => start,
+                None => {
+                    at = rest.len() - skip_spaces(&rest[at..], &self.opts).len();
+                    if !self.opts.allow_comments || !rest[at..].starts_with('/') {
+                        break;
+                    }
+                    at
+                }
+            };
+            match js_comment_from(&rest[start..], at - start) {
+                Some((len, true)) => at = start + len,
+                Some((len, false)) => {
+                    self.m.scan.comment = Some(start);
+                    at = start + len;
+                    break;
+                }
+                // Either a lone `/` that may start a comment, or not a comment at all
+                None if at + 1 == rest.len() => {
+                    self.m.scan.from = at;
+                    return false;
+                }
+                None => break,
+            }
+        }
+        self.m.scan.from = at;
+        at < rest.len()
+    }
+
+    fn open(&mut self, container: Container) -> Event<'a> {
+        self.m.stack.push(container);
+        self.m.items.push(0);
+        match container {
+            Container::Array => {
+                self.m.state = State::ArrayStart;
+                Event::StartArray
+            }
+            Container::Object => {
+                self.m.state = State::ObjectStart;
+                Event::StartObject
+            }
+        }
+    }
+
+    fn close(&mut self) -> Event<'a> {
+        self.m.state = State::AfterValue;
+        self.m.items.pop();
+        match self.m.stack.pop() {
+            Some(Container::Array) => Event::EndArray,
+            _ => Event::EndObject,
+        }
+    }
+
+    /// Counts an item of the innermost array or object, which starts here.
+    fn count_item(&mut self) -> Result<(), RawError<'a>> {
+        if let Some(count) = self.m.items.last_mut() {
+            match self.opts.max_items {
+                Some(max_items) if *count >= max_items => {
+                    return Err(RawError::new(
+                        self.rest,
+                        RawKind::TooManyItems { max_items },
+                    ))
+                }
+                _ => *count += 1,
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Event<'a>, RawError<'a>> {
+        if self.m.stack.last() == Some(&Container::Array) {
+            self.count_item().map_err(|e| self.with_context(e))?;
+        }
+        self.m.nodes += 1;
+        if let Some(max_nodes) = self.opts.max_nodes {
+            if self.m.nodes > max_nodes {
+                let e = RawError::new(self.rest, RawKind::TooManyNodes { max_nodes });
+                return Err(self.with_context(e));
+            }
+        }
+        if let Some(max_depth) = self.opts.max_depth {
+            if self.m.stack.len() >= max_depth && self.rest.starts_with(&['[', '{'][..]) {
+                let e = RawError::new(self.rest, RawKind::TooDeep { max_depth });
+                return Err(self.with_context(e));
+            }
+        }
+
+        let opts = self.opts.clone();
+        let event = self.parse(expect(
+            "value",
+            alt((
+                map(char('['), |_| Event::StartArray),
+                map(char('{'), |_| Event::StartObject),
+                map(tag("null"), |_| Event::Null),
+                map(tag("true"), |_| Event::Boolean(true)),
+                map(tag("false"), |_| Event::Boolean(false)),
+                map(|i| js_number(i, &opts), Event::Number),
+                map(|i| js_cow_string(i, &opts), Event::String),
+            )),
+        ))?;
+
+        Ok(match event {
+            Event::StartArray => self.open(Container::Array),
+            Event::StartObject => self.open(Container::Object),
+            event => {
+                self.m.state = State::AfterValue;
+                event
+            }
+        })
+    }
+
+    /// Parses the next event. Errors end the document.
+    pub(crate) fn step(&mut self) -> Result<Step<'a>, RawError<'a>> {
+        let res = match check_input_len(self.input, self.base.offset, &self.opts) {
+            Err(e) if self.m.state != State::Done => Err(e),
+            _ => self.step_inner(),
+        };
+        if res.is_err() {
+            self.m.state = State::Done;
+        }
+        res
+    }
+
+    fn step_inner(&mut self) -> Result<Step<'a>, RawError<'a>> {
+        loop {
+            self.skip_spaces()?;
+            if self.partial && !self.token_ready() {
+                return Ok(Step::NeedMore);
+            }
+            let start = self.m.position;
+
+            let event = match self.m.state {
+                // A stream may have no values at all
+                State::Value
+                    if self.m.stream && self.m.stack.is_empty() && self.rest.is_empty() =>
+                {
+                    self.m.state = State::Done;
+                    return Ok(Step::End);
+                }
+                State::Value => self.value()?,
+                State::ArrayStart => {
+                    if self.rest.starts_with(']') {
+                        self.parse(char(']'))?;
+                        self.close()
+                    } else {
+                        let rest = self.rest;
+                        self.value().map_err(|mut e| {
+                            // The recursive parser tried both before giving up
+                            if e.input.len() == rest.len() && e.expected == ["value"] {
+                                e.expected.insert(0, "']'");
+                            }
+                            e
+                        })?
+                    }
+                }
+                State::ObjectStart | State::Key => {
+                    let first = self.m.state == State::ObjectStart;
+                    if first && self.rest.starts_with('}') {
+                        self.parse(char('}'))?;
+                        self.close()
+                    } else {
+                        self.count_item().map_err(|mut e| {
+                            e.context.push("object");
+                            e
+                        })?;
+                        // From here on errors are in the member, like in the recursive parser
+                        self.m.state = State::Key;
+                        let (opts, rest) = (self.opts.clone(), self.rest);
+                        let label = key_label(&opts);
+                        let key = self.parse(|i| js_key(i, &opts)).map_err(|mut e| {
+                            if first && e.input.len() == rest.len() && e.expected == [label] {
+                                // The recursive parser tried `}` as well, outside of the member
+                                e.expected.insert(0, "'}'");
+                                e.context = vec!["object"];
+                            }
+                            e
+                        })?;
+                        self.key_end = self.m.position;
+                        self.skip_spaces()?;
+                        self.parse(char(':'))?;
+                        self.m.state = State::Value;
+                        Event::Key(key)
+                    }
+                }
+                State::AfterValue => {
+                    let close = match self.m.stack.last() {
+                        Some(Container::Array) => ']',
+                        Some(Container::Object) => '}',
+                        None if self.rest.is_empty() => {
+                            self.m.state = State::Done;
+                            return Ok(Step::End);
+                        }
+                        None if self.m.stream => {
+                            self.m.state = State::Value;
+                            self.m.nodes = 0;
+                            continue;
+                        }
+                        None => return Err(RawError::expected(self.rest, "end of input")),
+                    };
+
+                    if self.parse(alt((map(char(','), |_| false), map(char(close), |_| true))))? {
+                        self.close()
+                    } else {
+                        self.m.state = match (self.m.stack.last(), self.opts.allow_trailing_commas)
+                        {
+                            (Some(Container::Object), false) => State::Key,
+                            (Some(Container::Object), true) => State::ObjectStart,
+                            (_, false) => State::Value,
+                            (_, true) => State::ArrayStart,
+                        };
+                        continue;
+                    }
+                }
+                State::Done => return Ok(Step::End),
+            };
+
+            return Ok(Step::Event(start, event));
+        }
+    }
+}
+
+/// Length of the string literal at the start of `s` if it is closed, or else where to
+/// go on looking for its end once more of it is there. The search starts at `from`,
+/// which is 0 or what an earlier call returned.
+fn string_len(s: &str, from: usize) -> Result<usize, usize> {
+    let bytes = s.as_bytes();
+    let quote = bytes[0];
+    let mut i = cmp::max(1, from);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return Ok(i + 1),
+            _ => i += 1,
+        }
+    }
+    Err(i)
+}
+
+impl<'a> Iterator for Parser<'a> {
+    type Item = Result<(Position, Event<'a>), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step() {
+            Ok(Step::Event(pos, event)) => Some(Ok((pos, event))),
+            Ok(Step::NeedMore) | Ok(Step::End) => None,
+            Err(e) => Some(Err(self.error(e))),
+        }
+    }
+}
+
+/// Assembles values from events, keeping unfinished arrays and objects on a stack of
+/// its own instead of recursing.
+pub(crate) struct Builder<'a, V: Tree<'a>> {
+    stack: Vec<Partial<'a, V>>,
+    duplicate_keys: DuplicateKeys,
+}
+
+enum Partial<'a, V: Tree<'a>> {
+    Array(Vec<V>),
+    Object {
+        map: Map<V::Key, V>,
+        /// Key for the value being built, `None` if the value is to be dropped
+        key: Option<V::Key>,
+        /// Where each key of `map` starts, for `DuplicateKeys::Error`
+        positions: Vec<Position>,
+    },
+}
+
+/// The parser may give up on a document of any depth, so what was built of it is
+/// dropped without recursing.
+impl<'a, V: Tree<'a>> Drop for Builder<'a, V> {
+    fn drop(&mut self) {
+        drop_iteratively(self.stack.drain(..).flat_map(|partial| match partial {
+            Partial::Array(items) => items,
+            Partial::Object { map, .. } => map.into_iter().map(|(_, v)| v).collect(),
+        }));
+    }
+}
+
+impl<'a, V: Tree<'a>> Builder<'a, V> {
+    pub(crate) fn new(duplicate_keys: DuplicateKeys) -> Self {
+        Builder {
+            stack: Vec::new(),
+            duplicate_keys,
+        }
+    }
+
+    /// Whether no array or object is being built.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Takes the next event, returning the value once it is complete.
+    ///
+    /// Fails on a repeated key with [`DuplicateKeys::Error`].
+    pub(crate) fn push(
+        &mut self,
+        pos: Position,
+        event: Event<'a>,
+    ) -> Result<Option<V>, RawKind<'a>> {
+        let value = match event {
+            Event::StartArray => {
+                self.stack.push(Partial::Array(Vec::new()));
+                return Ok(None);
+            }
+            Event::StartObject => {
+                self.stack.push(Partial::Object {
+                    map: Map::new(),
+                    key: None,
+                    positions: Vec::new(),
+                });
+                return Ok(None);
+            }
+            Event::Key(k) => {
+                if let Some(Partial::Object {
+                    map,
+                    key,
+                    positions,
+                }) = self.stack.last_mut()
+                {
+                    let k = V::key(k);
+                    match self.duplicate_keys {
+                        DuplicateKeys::Error => {
+                            if let Some(idx) = map.get_index_of(&k) {
+                                let key = k.as_ref().to_string();
+                                let first = positions[idx];
+                                return Err(RawKind::DuplicateKeyAt { key, first });
+                            }
+                            positions.push(pos);
+                            *key = Some(k);
+                        }
+                        DuplicateKeys::FirstWins if map.contains_key(&k) => *key = None,
+                        _ => *key = Some(k),
+                    }
+                }
+                return Ok(None);
+            }
+            Event::EndArray | Event::EndObject => match self.stack.pop() {
+                Some(Partial::Array(items)) => V::array(items),
+                Some(Partial::Object { map, .. }) => V::object(map),
+                None => return Ok(None),
+            },
+            Event::String(s) => V::string(s),
+            Event::Number(n) => V::number(n),
+            Event::Boolean(b) => V::boolean(b),
+            Event::Null => V::null(),
+        };
+
+        match self.stack.last_mut() {
+            None => return Ok(Some(value)),
+            Some(Partial::Array(items)) => items.push(value),
+            Some(Partial::Object { map, key, .. }) => {
+                if let Some(k) = key.take() {
+                    if self.duplicate_keys == DuplicateKeys::KeepAll {
+                        map.append(k, value);
+                    } else {
+                        map.insert(k, value);
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Parses a whole document without recursing, for [`ParseOptions::iterative`].
+///
+/// The length of the input is left for the caller to check, `input` may have been
+/// converted from bytes of another length.
+pub(crate) fn build<'a, V: Tree<'a>>(input: &'a str, opts: &ParseOptions) -> Result<V, ParseError> {
+    let opts = ParseOptions {
+        max_input_len: None,
+        ..opts.clone()
+    };
+    let mut parser = Parser::with_options(input, opts.clone());
+    let mut builder = Builder::new(opts.duplicate_keys);
+    let mut value = None;
+
+    loop {
+        match parser.step() {
+            Ok(Step::Event(pos, event)) => match builder.push(pos, event) {
+                Ok(Some(v)) => value = Some(v),
+                Ok(None) => {}
+                Err(kind) => return Err(parser.key_error(pos, kind)),
+            },
+            // The parser only ends after a complete value
+            Ok(Step::End) | Ok(Step::NeedMore) => return Ok(value.unwrap()),
+            Err(e) => {
+                // Input after the value may be wrong
+                drop_iteratively(value);
+                return Err(parser.error(e));
+            }
+        }
+    }
+}
+
+#[test]
+fn events_test() {
+    let events: Vec<_> = Parser::new("{\"a\": [1, true],\n \"b\\n\": {}}")
+        .map(|e| e.map(|(pos, e)| (pos.line, pos.column, e)))
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        events,
+        vec![
+            (1, 1, Event::StartObject),
+            (1, 2, Event::Key("a".into())),
+            (1, 7, Event::StartArray),
+            (1, 8, Event::Number(1.into())),
+            (1, 11, Event::Boolean(true)),
+            (1, 15, Event::EndArray),
+            (2, 2, Event::Key("b\n".into())),
+            (2, 9, Event::StartObject),
+            (2, 10, Event::EndObject),
+            (2, 11, Event::EndObject),
+        ]
+    );
+}
+
+#[test]
+fn error_test() {
+    let mut parser = Parser::new("[1, {\"a\" 2}]");
+    for _ in 0..3 {
+        parser.next().unwrap().unwrap();
+    }
+
+    let err = parser.next().unwrap().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected ':', found `2` at line 1, column 10 in object item"
+    );
+    assert!(parser.next().is_none());
+
+    let mut parser = Parser::new("[] []");
+    assert_eq!(parser.by_ref().count(), 3);
+    assert!(parser.next().is_none());
+
+    let err = Parser::new("[1 2]").last().unwrap().unwrap_err();
+    assert_eq!(err.expected(), ["','", "']'"]);
+}
@@ -0,0 +1,533 @@
+//! Push parser for JSON that arrives in chunks, e.g. from a socket.
+//!
+//! Chunks can be split anywhere, even in the middle of a token or a UTF-8 sequence.
+//!
+//! ```
+//! use json_rs_prac::push::{PushParser, Status};
+//! use json_rs_prac::Value;
+//!
+//! let mut parser = PushParser::new();
+//! parser.feed(b"[1, tr").unwrap();
+//! assert!(matches!(parser.next_value(), Ok(Status::NeedMore)));
+//!
+//! parser.feed(b"ue]").unwrap();
+//! parser.finish();
+//! match parser.next_value().unwrap() {
+//!     Status::Ready(value) => assert_eq!(value, json_rs_prac::from_str("[1, true]").unwrap()),
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+use std::mem;
+use std::str;
+
+use crate::error::{RawError, RawKind};
+use crate::pull::{Builder, Event, Machine, Parser, Step};
+use crate::{Encoding, Error, ParseError, ParseOptions, Position, Value};
+
+/// Result of asking a [`PushParser`] for more output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status<T> {
+    Ready(T),
+    /// Everything fed so far has been used up, call [`PushParser::feed`] or
+    /// [`PushParser::finish`].
+    NeedMore,
+    /// The document is complete, or an error was returned before.
+    Done,
+}
+
+/// Parses a single JSON value from input that is fed to it piece by piece.
+///
+/// Only the part of the input that wasn't parsed yet is kept in memory, plus one entry
+/// per open array or object. Events are reported as soon as they are complete; a token
+/// running up to the end of the input so far is held back until more input or
+/// [`finish`](PushParser::finish) shows where it ends.
+pub struct PushParser {
+    buf: String,
+    /// Bytes at the start of `buf` that were already parsed.
+    consumed: usize,
+    /// Position of the start of `buf` in the document.
+    buf_start: Position,
+    /// Start of a UTF-8 sequence that was split between chunks.
+    pending: Vec<u8>,
+    opts: ParseOptions,
+    machine: Machine,
+    builder: Builder<'static, Value>,
+    /// Where the value being built starts.
+    value_start: Position,
+    finished: bool,
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        PushParser::with_options(ParseOptions::default())
+    }
+
+    pub fn with_options(opts: ParseOptions) -> Self {
+        PushParser::with_machine(opts, Machine::new())
+    }
+
+    /// A parser for any number of values one after the other, see
+    /// [`Framing::Concatenated`](crate::stream::Framing::Concatenated).
+    pub(crate) fn stream(opts: ParseOptions) -> Self {
+        PushParser::with_machine(opts, Machine::stream())
+    }
+
+    fn with_machine(opts: ParseOptions, machine: Machine) -> Self {
+        PushParser {
+            buf: String::new(),
+            consumed: 0,
+            buf_start: Position::START,
+            pending: Vec::new(),
+            builder: Builder::new(opts.duplicate_keys),
+            opts,
+            machine,
+            value_start: Position::START,
+            finished: false,
+        }
+    }
+
+    /// Where parsing got to, i.e. right after the last event.
+    pub(crate) fn position(&self) -> Position {
+        self.machine.position()
+    }
+
+    /// Adds the next chunk of input.
+    ///
+    /// Fails with [`ParseErrorKind::InvalidEncoding`](crate::ParseErrorKind::InvalidEncoding)
+    /// if the input isn't valid UTF-8, positioned in the whole input. A sequence cut off
+    /// at the end of `chunk` is fine, it is completed by the next chunk. Also fails once
+    /// the input gets longer than [`max_input_len`](ParseOptions::max_input_len),
+    /// without keeping the excess.
+    ///
+    /// # Panics
+    ///
+    /// If called after [`finish`](PushParser::finish).
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        assert!(!self.finished, "PushParser::feed called after finish");
+
+        // Drop what was parsed already, it is not needed anymore
+        if self.consumed > 0 {
+            self.buf.drain(..self.consumed);
+            self.consumed = 0;
+            self.buf_start = self.machine.position();
+        }
+
+        let mut chunk = chunk;
+        let mut too_long = None;
+        if let Some(max_input_len) = self.opts.max_input_len {
+            let room =
+                max_input_len - (self.buf_start.offset + self.buf.len() + self.pending.len());
+            if chunk.len() > room {
+                chunk = &chunk[..room];
+                too_long = Some(max_input_len);
+            }
+        }
+
+        let joined;
+        let mut bytes = chunk;
+        if !self.pending.is_empty() {
+            self.pending.extend_from_slice(chunk);
+            joined = mem::take(&mut self.pending);
+            bytes = &joined;
+        }
+
+        match str::from_utf8(bytes) {
+            Ok(s) => self.buf.push_str(s),
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                self.buf.push_str(str::from_utf8(valid).unwrap());
+                if e.error_len().is_some() {
+                    return Err(self.error_at_end(RawKind::InvalidEncoding {
+                        encoding: Encoding::Utf8,
+                    }));
+                }
+                self.pending = rest.to_vec();
+            }
+        }
+
+        if let Some(max_input_len) = too_long {
+            return Err(self.error_at_end(RawKind::InputTooLong { max_input_len }));
+        }
+
+        Ok(())
+    }
+
+    /// Ends the document with an error right after the input fed so far.
+    fn error_at_end(&mut self, kind: RawKind) -> Error {
+        let e = RawError::new(&self.buf[self.buf.len()..], kind);
+        self.machine.stop();
+        Error::Parse(ParseError::with_base(&self.buf, e, self.buf_start))
+    }
+
+    /// Marks the end of the input, so whatever is left gets parsed.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Returns the next event of the document.
+    pub fn next_event(&mut self) -> Result<Status<(Position, Event<'static>)>, Error> {
+        if self.finished && !self.pending.is_empty() {
+            self.pending.clear();
+            return Err(self.error_at_end(RawKind::InvalidEncoding {
+                encoding: Encoding::Utf8,
+            }));
+        }
+
+        let machine = mem::replace(&mut self.machine, Machine::new());
+        let mut parser = Parser::resume(
+            &self.buf[self.consumed..],
+            self.opts.clone(),
+            machine,
+            !self.finished,
+        );
+
+        let res = match parser.step() {
+            Ok(Step::Event(pos, event)) => Ok(Status::Ready((pos, event.into_owned()))),
+            Ok(Step::NeedMore) => Ok(Status::NeedMore),
+            Ok(Step::End) => Ok(Status::Done),
+            Err(e) => Err(Error::Parse(parser.error(e))),
+        };
+
+        self.consumed += parser.consumed();
+        self.machine = parser.into_machine();
+        res
+    }
+
+    /// Returns the document as a [`Value`] once it is complete.
+    ///
+    /// Parts of the value are built as their input arrives, so the input doesn't have
+    /// to be held in memory until the end. Input after the value is only checked by the
+    /// next call, which returns [`Status::Done`] if there is nothing but whitespace.
+    pub fn next_value(&mut self) -> Result<Status<Value>, Error> {
+        Ok(match self.next_value_at()? {
+            Status::Ready((_, value)) => Status::Ready(value),
+            Status::NeedMore => Status::NeedMore,
+            Status::Done => Status::Done,
+        })
+    }
+
+    /// Like [`next_value`](PushParser::next_value), also returning where the value starts.
+    pub(crate) fn next_value_at(&mut self) -> Result<Status<(Position, Value)>, Error> {
+        loop {
+            let (pos, event) = match self.next_event()? {
+                Status::Ready(event) => event,
+                Status::NeedMore => return Ok(Status::NeedMore),
+                Status::Done => return Ok(Status::Done),
+            };
+
+            if self.builder.is_empty() {
+                self.value_start = pos;
+            }
+            match self.builder.push(pos, event) {
+                Ok(Some(value)) => return Ok(Status::Ready((self.value_start, value))),
+                Ok(None) => {}
+                Err(kind) => {
+                    // The key was parsed by the last call, so it is still in `buf`
+                    let key = &self.buf[pos.offset - self.buf_start.offset..];
+                    let mut e = RawError::new(key, kind);
+                    e.context.push("object");
+                    self.machine.stop();
+                    return Err(Error::Parse(ParseError::with_base(
+                        &self.buf,
+                        e,
+                        self.buf_start,
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new()
+    }
+}
+
+#[cfg(test)]
+const DOCUMENTS: &[&str] = &[
+    include_str!("../example.json"),
+    "{\"a\\u00e9\\ud83d\\ude00\": [-1.5e3, 0, true, false, null, \"\u{e9}\u{1f600}\"]}",
+    "  [ [], {}, [[1]], {\"\": {\"x\": \"\\\"\"}} ]  ",
+    "123",
+    "\"abc\"",
+    "[1, 2",
+    "[1 2]",
+    "{\"a\": tru}",
+    "[\"\\u12\"]",
+    "1 2",
+];
+
+/// Feeds `chunks` and collects every event, or the error.
+#[cfg(test)]
+fn collect_events(
+    opts: ParseOptions,
+    chunks: &[&[u8]],
+) -> Result<Vec<(Position, Event<'static>)>, String> {
+    let mut parser = PushParser::with_options(opts);
+    let mut events = Vec::new();
+
+    for (idx, chunk) in chunks.iter().enumerate() {
+        parser.feed(chunk).map_err(|e| e.to_string())?;
+        if idx == chunks.len() - 1 {
+            parser.finish();
+        }
+
+        loop {
+            match parser.next_event().map_err(|e| e.to_string())? {
+                Status::Ready(event) => events.push(event),
+                Status::NeedMore if idx < chunks.len() - 1 => break,
+                Status::NeedMore => panic!("NeedMore after finish"),
+                Status::Done => return Ok(events),
+            }
+        }
+    }
+
+    unreachable!()
+}
+
+#[test]
+fn split_test() {
+    for doc in DOCUMENTS {
+        let expected: Result<Vec<_>, String> = Parser::new(doc)
+            .map(|e| e.map(|(pos, e)| (pos, e.into_owned())))
+            .collect::<Result<_, _>>()
+            .map_err(|e| crate::Error::Parse(e).to_string());
+        let bytes = doc.as_bytes();
+
+        // At every byte offset, also splitting UTF-8 sequences
+        for at in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(at);
+            let events = collect_events(ParseOptions::default(), &[a, b]);
+            assert_eq!(events, expected, "{:?} split at {}", doc, at);
+        }
+
+        let single: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(
+            collect_events(ParseOptions::default(), &single),
+            expected,
+            "{:?}",
+            doc
+        );
+    }
+}
+
+#[test]
+fn value_test() {
+    for doc in DOCUMENTS {
+        let bytes = doc.as_bytes();
+        let expected = crate::from_str(doc).map_err(|e| e.to_string());
+
+        for at in 0..=bytes.len() {
+            let mut parser = PushParser::new();
+            parser.feed(&bytes[..at]).unwrap();
+            let mut early = parser.next_value().map_err(|e| e.to_string());
+            if early == Ok(Status::NeedMore) {
+                parser.feed(&bytes[at..]).unwrap();
+                parser.finish();
+                early = parser.next_value().map_err(|e| e.to_string());
+            } else {
+                parser.feed(&bytes[at..]).unwrap();
+                parser.finish();
+            }
+
+            // Input after the value is only checked by the next call
+            let value = early.and_then(|status| match status {
+                Status::Ready(value) => match parser.next_value() {
+                    Ok(Status::Done) => Ok(value),
+                    Ok(status) => panic!("{:?}", status),
+                    Err(e) => Err(e.to_string()),
+                },
+                status => panic!("{:?}", status),
+            });
+            assert_eq!(value, expected, "{:?} split at {}", doc, at);
+        }
+    }
+}
+
+#[test]
+fn duplicate_key_test() {
+    let opts = ParseOptions {
+        duplicate_keys: crate::DuplicateKeys::Error,
+        ..Default::default()
+    };
+    let doc = "{\"a\": 1,\n \"a\": 2}";
+
+    let mut parser = PushParser::with_options(opts.clone());
+    parser.feed(doc.as_bytes()).unwrap();
+    parser.finish();
+    assert_eq!(
+        parser.next_value().unwrap_err().to_string(),
+        opts.parse_str(doc).unwrap_err().to_string()
+    );
+    assert!(matches!(parser.next_value(), Ok(Status::Done)));
+}
+
+#[test]
+fn utf8_test() {
+    let mut parser = PushParser::new();
+    parser.feed(b"\"\xc3").unwrap();
+    parser.feed(b"\xa9\"").unwrap();
+    parser.finish();
+    assert_eq!(
+        parser.next_value().unwrap(),
+        Status::Ready(Value::String("\u{e9}".into()))
+    );
+
+    let invalid = |res: Result<_, Error>| match res {
+        Err(Error::Parse(e)) => {
+            assert_eq!(
+                e.kind(),
+                &crate::ParseErrorKind::InvalidEncoding {
+                    encoding: Encoding::Utf8
+                }
+            );
+            (e.offset(), e.line(), e.column())
+        }
+        res => panic!("{:?}", res.map(|_| ())),
+    };
+
+    let mut parser = PushParser::new();
+    parser.feed(b"\"\xc3").unwrap();
+    parser.finish();
+    assert_eq!(invalid(parser.next_value().map(|_| ())), (1, 1, 2));
+    assert!(matches!(parser.next_value(), Ok(Status::Done)));
+
+    assert_eq!(invalid(PushParser::new().feed(b"\"\xff\"")), (1, 1, 2));
+
+    // Offsets count from the start of the document, not of the chunk
+    let mut parser = PushParser::new();
+    parser.feed(b"[1,\n \"\xc3").unwrap();
+    assert!(matches!(parser.next_event(), Ok(Status::Ready(_))));
+    assert!(matches!(parser.next_event(), Ok(Status::Ready(_))));
+    assert_eq!(invalid(parser.feed(b"(\"]")), (6, 2, 3));
+    assert!(matches!(parser.next_value(), Ok(Status::Done)));
+}
+
+#[test]
+fn limits_test() {
+    let opts = ParseOptions {
+        max_input_len: Some(5),
+        max_items: Some(1),
+        ..Default::default()
+    };
+
+    let mut parser = PushParser::with_options(opts.clone());
+    parser.feed(b"[1").unwrap();
+    assert!(matches!(parser.next_value(), Ok(Status::NeedMore)));
+    match parser.feed(b", 2, 3]") {
+        Err(Error::Parse(e)) => {
+            assert_eq!(
+                e.kind(),
+                &crate::ParseErrorKind::InputTooLong { max_input_len: 5 }
+            );
+            assert_eq!(e.offset(), 5);
+        }
+        res => panic!("{:?}", res),
+    }
+    assert!(matches!(parser.next_value(), Ok(Status::Done)));
+
+    let mut parser = PushParser::with_options(opts.clone());
+    parser.feed(b"[1,").unwrap();
+    parser.feed(b"2]").unwrap();
+    parser.finish();
+    assert_eq!(
+        parser.next_value().unwrap_err().to_string(),
+        opts.parse_str("[1,2]").unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn comments_test() {
+    let docs = [
+        "// a\n{\"a\" /* b */ : [1, /**/2,], \"c\"// d\n: tru//e\n, }",
+        "[1, 2,] // end",
+        "/* open [1",
+        "[1 /",
+        "//",
+        "{\"a\" /* ** / */ /**/ // c\n : \"\\\\*/\"}",
+        "{\"a\" / : 1}",
+        "{\"a\" /* b",
+    ];
+
+    for doc in &docs {
+        let expected: Result<Vec<_>, String> = Parser::with_options(doc, ParseOptions::jsonc())
+            .map(|e| e.map(|(pos, e)| (pos, e.into_owned())))
+            .collect::<Result<_, _>>()
+            .map_err(|e| crate::Error::Parse(e).to_string());
+        let bytes = doc.as_bytes();
+
+        for at in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(at);
+            let events = collect_events(ParseOptions::jsonc(), &[a, b]);
+            assert_eq!(events, expected, "{:?} split at {}", doc, at);
+        }
+
+        let single: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(
+            collect_events(ParseOptions::jsonc(), &single),
+            expected,
+            "{:?}",
+            doc
+        );
+    }
+}
+
+#[test]
+fn long_token_test() {
+    // Each byte is only looked at a few times, however small the chunks
+    let long = "x".repeat(100_000);
+    let docs = [
+        format!("[\"{}\"]", long),
+        format!("[0.{}1]", "0".repeat(100_000)),
+        format!("[/*{}*/ 1]", long),
+        format!("{{\"{}\": 1}}", long),
+        format!("{{\"a\"{} // {}\n: 1}}", " ".repeat(100_000), long),
+    ];
+
+    for doc in &docs {
+        let single: Vec<&[u8]> = doc.as_bytes().chunks(1).collect();
+        let events = collect_events(ParseOptions::jsonc(), &single).unwrap();
+        let expected = Parser::with_options(doc, ParseOptions::jsonc()).count();
+        assert_eq!(events.len(), expected, "{:.20}", doc);
+    }
+}
+
+#[test]
+fn json5_test() {
+    let docs = [
+        "{unquoted: 'a\\\nb', $x\\u0041 /* c */ : [0x1F, +.5, -Infinity, NaN,], \"k\": '\"',}",
+        "\u{feff}\u{a0}['\u{e9}',\u{2028}1.]",
+        "{a b: 1}",
+        "{a: 'b\nc'}",
+    ];
+
+    for doc in &docs {
+        let expected: Result<Vec<_>, String> = Parser::with_options(doc, ParseOptions::json5())
+            .map(|e| e.map(|(pos, e)| (pos, e.into_owned())))
+            .collect::<Result<_, _>>()
+            .map_err(|e| crate::Error::Parse(e).to_string());
+        let bytes = doc.as_bytes();
+
+        for at in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(at);
+            let events = collect_events(ParseOptions::json5(), &[a, b]);
+            assert_eq!(
+                format!("{:?}", events),
+                format!("{:?}", expected),
+                "{:?} split at {}",
+                doc,
+                at
+            );
+        }
+
+        let single: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(
+            format!("{:?}", collect_events(ParseOptions::json5(), &single)),
+            format!("{:?}", expected),
+            "{:?}",
+            doc
+        );
+    }
+}
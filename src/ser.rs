@@ -0,0 +1,754 @@
+//! Serializing Rust types as JSON text with [serde](https://serde.rs).
+//!
+//! The [`Serializer`] writes JSON as it goes, no [`Value`] is built in between.
+
+use std::fmt::Write as _;
+use std::io;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::write::{write_string, IoWriter};
+use crate::{Error, Map, Number, Value, WriteOptions};
+
+/// Writes serde data as JSON into an `io::Write`.
+pub struct Serializer<W> {
+    writer: IoWriter<W>,
+    indent: Option<String>,
+    depth: usize,
+}
+
+impl<W: io::Write> Serializer<W> {
+    /// Serializer writing compact JSON.
+    pub fn new(writer: W) -> Self {
+        Serializer::with_options(writer, &WriteOptions::default())
+    }
+
+    /// Serializer writing JSON indented with two spaces.
+    pub fn pretty(writer: W) -> Self {
+        Serializer::with_options(writer, &WriteOptions::pretty())
+    }
+
+    /// Members are written in the order they are serialized, so
+    /// [`sort_keys`](WriteOptions::sort_keys) has no effect here.
+    pub fn with_options(writer: W, opts: &WriteOptions) -> Self {
+        Serializer {
+            writer: IoWriter {
+                inner: writer,
+                error: None,
+            },
+            indent: opts.indent.clone(),
+            depth: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.inner
+    }
+
+    fn io_error(&mut self) -> Error {
+        Error::Io(
+            self.writer
+                .error
+                .take()
+                .unwrap_or_else(|| io::Error::other("formatter error")),
+        )
+    }
+
+    fn write(&mut self, s: &str) -> Result<(), Error> {
+        self.writer.write_str(s).map_err(|_| self.io_error())
+    }
+
+    fn write_display(&mut self, value: impl std::fmt::Display) -> Result<(), Error> {
+        write!(self.writer, "{}", value).map_err(|_| self.io_error())
+    }
+
+    fn write_string(&mut self, s: &str) -> Result<(), Error> {
+        write_string(&mut self.writer, s).map_err(|_| self.io_error())
+    }
+
+    fn write_newline(&mut self) -> Result<(), Error> {
+        if let Some(indent) = &self.indent {
+            let mut buf = String::from("\n");
+            for _ in 0..self.depth {
+                buf.push_str(indent);
+            }
+            self.write(&buf)?;
+        }
+        Ok(())
+    }
+
+    fn begin(&mut self, open: &str) -> Result<(), Error> {
+        self.depth += 1;
+        self.write(open)
+    }
+
+    /// Empty arrays and objects stay on one line, just like [`WriteOptions`] does.
+    fn end(&mut self, close: &str, empty: bool) -> Result<(), Error> {
+        self.depth -= 1;
+        if !empty {
+            self.write_newline()?;
+        }
+        self.write(close)
+    }
+
+    fn element(&mut self, first: bool) -> Result<(), Error> {
+        if !first {
+            self.write(",")?;
+        }
+        self.write_newline()
+    }
+
+    fn key(&mut self, first: bool, key: &str) -> Result<(), Error> {
+        self.element(first)?;
+        self.write_string(key)?;
+        self.write(if self.indent.is_some() { ": " } else { ":" })
+    }
+
+    fn float(&mut self, f: impl std::fmt::Debug, finite: bool) -> Result<(), Error> {
+        // JSON has no NaN or infinity
+        if finite {
+            self.write(&format!("{:?}", f))
+        } else {
+            self.write("null")
+        }
+    }
+}
+
+impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write(if v { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        self.write_display(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.float(v, v.is_finite())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.float(v, v.is_finite())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write_string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write_string(v)
+    }
+
+    /// Bytes become an array of numbers.
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        use serde::ser::SerializeSeq;
+        let mut seq = self.serialize_seq(Some(v.len()))?;
+        for b in v {
+            seq.serialize_element(b)?;
+        }
+        SerializeSeq::end(seq)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.write("null")
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.write("null")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.write("null")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.write_string(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        // A number that is only exact as text, see `impl Serialize for Number`
+        if cfg!(feature = "arbitrary_precision") && name == crate::number::TOKEN {
+            let text = value.serialize(MapKey)?;
+            return self.write(&text);
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.begin("{")?;
+        self.key(true, variant)?;
+        value.serialize(&mut *self)?;
+        self.end("}", false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>, Error> {
+        self.begin("[")?;
+        Ok(Compound::new(self, false))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    /// Variants with data become an object with the variant name as its only key.
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, Error> {
+        self.begin("{")?;
+        self.key(true, variant)?;
+        self.begin("[")?;
+        Ok(Compound::new(self, true))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>, Error> {
+        self.begin("{")?;
+        Ok(Compound::new(self, false))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a, W>, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, Error> {
+        self.begin("{")?;
+        self.key(true, variant)?;
+        self.begin("{")?;
+        Ok(Compound::new(self, true))
+    }
+}
+
+/// Array or object being written.
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    first: bool,
+    /// Whether this sits inside a `{"Variant": ...}` object that also needs closing.
+    variant: bool,
+}
+
+impl<'a, W: io::Write> Compound<'a, W> {
+    fn new(ser: &'a mut Serializer<W>, variant: bool) -> Self {
+        Compound {
+            ser,
+            first: true,
+            variant,
+        }
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.ser.element(self.first)?;
+        self.first = false;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        self.ser.key(self.first, key)?;
+        self.first = false;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self, close: &str) -> Result<(), Error> {
+        self.ser.end(close, self.first)?;
+        if self.variant {
+            self.ser.end("}", false)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        let key = key.serialize(MapKey)?;
+        self.ser.key(self.first, &key)?;
+        self.first = false;
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self, "}")
+    }
+}
+
+impl<W: io::Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self, "}")
+    }
+}
+
+impl<W: io::Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self, "}")
+    }
+}
+
+/// Turns a map key into the string written before the `:`. Only strings, chars and
+/// integers make sense as object keys.
+struct MapKey;
+
+fn key_error() -> Error {
+    ser::Error::custom("object key must be a string")
+}
+
+macro_rules! serialize_display_key {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<String, Error> {
+                Ok(v.to_string())
+            }
+        )*
+    };
+}
+
+macro_rules! reject_key {
+    ($($method:ident($($ty:ty),*),)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<String, Error> {
+                Err(key_error())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for MapKey {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    serialize_display_key! {
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    reject_key! {
+        serialize_bool(bool),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(key_error())
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Data {
+            message: msg.to_string(),
+            position: None,
+        }
+    }
+}
+
+/// Writes `value` as compact JSON into `writer`.
+pub fn to_writer<T: ?Sized + Serialize>(writer: impl io::Write, value: &T) -> Result<(), Error> {
+    value.serialize(&mut Serializer::new(writer))
+}
+
+/// Writes `value` as JSON indented with two spaces into `writer`.
+pub fn to_writer_pretty<T: ?Sized + Serialize>(
+    writer: impl io::Write,
+    value: &T,
+) -> Result<(), Error> {
+    value.serialize(&mut Serializer::pretty(writer))
+}
+
+/// Serializes `value` as compact JSON.
+pub fn to_vec<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    to_writer(&mut buf, value)?;
+    Ok(buf)
+}
+
+/// Serializes `value` as compact JSON.
+///
+/// ```
+/// #[derive(serde::Serialize)]
+/// struct Entry {
+///     id: u64,
+///     tags: Vec<&'static str>,
+/// }
+///
+/// let entry = Entry { id: 7, tags: vec!["a"] };
+/// assert_eq!(json_rs_prac::ser::to_string(&entry).unwrap(), r#"{"id":7,"tags":["a"]}"#);
+/// ```
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String, Error> {
+    // The serializer only ever writes valid UTF-8
+    Ok(String::from_utf8(to_vec(value)?).unwrap())
+}
+
+/// Serializes `value` as JSON indented with two spaces.
+pub fn to_string_pretty<T: ?Sized + Serialize>(value: &T) -> Result<String, Error> {
+    let mut buf = Vec::new();
+    to_writer_pretty(&mut buf, value)?;
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => s.serialize_unit(),
+            Value::Boolean(b) => s.serialize_bool(*b),
+            Value::Number(n) => n.serialize(s),
+            Value::String(str) => s.serialize_str(str),
+            Value::Array(arr) => arr.serialize(s),
+            Value::Object(obj) => obj.serialize(s),
+        }
+    }
+}
+
+/// Numbers that only fit a decimal string (with `arbitrary_precision`) are serialized
+/// as a newtype struct around that string. [`Serializer`] writes the text as it is,
+/// other serializers get the string instead of a rounded `f64`.
+impl Serialize for Number {
+    fn serialize<S: ser::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        #[cfg(feature = "arbitrary_precision")]
+        {
+            if let Some(text) = self.as_decimal() {
+                return s.serialize_newtype_struct(crate::number::TOKEN, text);
+            }
+        }
+        if let Some(n) = self.as_u64() {
+            s.serialize_u64(n)
+        } else if let Some(n) = self.as_i64() {
+            s.serialize_i64(n)
+        } else {
+            s.serialize_f64(self.to_f64())
+        }
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for Map<K, V> {
+    fn serialize<S: ser::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(self.iter())
+    }
+}
+
+#[test]
+fn struct_test() {
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Kind {
+        Plain,
+        Sized(u32),
+        Pair(i8, i8),
+        Point { x: i8, y: i8 },
+    }
+
+    #[derive(Serialize)]
+    struct Config {
+        name: &'static str,
+        ratio: Option<f64>,
+        kinds: Vec<Kind>,
+        counts: BTreeMap<u32, bool>,
+        empty: Vec<()>,
+    }
+
+    let config = Config {
+        name: "a\"b",
+        ratio: Some(f64::NAN),
+        kinds: vec![
+            Kind::Plain,
+            Kind::Sized(3),
+            Kind::Pair(1, 2),
+            Kind::Point { x: -1, y: 2 },
+        ],
+        counts: vec![(1, true)].into_iter().collect(),
+        empty: vec![],
+    };
+
+    assert_eq!(
+        to_string(&config).unwrap(),
+        r#"{"name":"a\"b","ratio":null,"kinds":["Plain",{"Sized":3},{"Pair":[1,2]},{"Point":{"x":-1,"y":2}}],"counts":{"1":true},"empty":[]}"#
+    );
+    assert!(to_string(&vec![(vec![1], 2)].into_iter().collect::<BTreeMap<_, _>>()).is_err());
+}
+
+#[test]
+fn value_test() {
+    use serde::Serialize;
+
+    let value = crate::from_str(include_str!("../example.json")).unwrap();
+    assert_eq!(to_string(&value).unwrap(), crate::to_string(&value));
+    assert_eq!(
+        to_string_pretty(&value).unwrap(),
+        crate::to_string_pretty(&value)
+    );
+
+    #[derive(Serialize)]
+    enum Shape {
+        Line(Vec<u8>),
+    }
+    assert_eq!(
+        to_string_pretty(&Shape::Line(vec![1])).unwrap(),
+        "{\n  \"Line\": [\n    1\n  ]\n}"
+    );
+}
+
+#[cfg(feature = "arbitrary_precision")]
+#[test]
+fn decimal_test() {
+    let text = "[3.14159265358979323846264338327950288,1e400,-0.1e-400]";
+    let value = crate::from_str(text).unwrap();
+    assert_eq!(to_string(&value).unwrap(), text);
+    assert_eq!(to_string(&value).unwrap(), crate::to_string(&value));
+}
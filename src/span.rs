@@ -0,0 +1,324 @@
+//! Where the values and keys of a document are in its text, for diagnostics that point
+//! at the input like `config.json:12:7`.
+
+use std::collections::HashMap;
+
+use crate::pointer;
+use crate::pull::{Builder, Event, Parser, Step};
+use crate::{
+    check_input_len, drop_iteratively, DuplicateKeys, Error, Map, ParseError, ParseOptions,
+    Position, Value,
+};
+
+/// A stretch of the input, from `start` up to but not including `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Where a value sits in the input, and its key if it is an object member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeSpan {
+    pub value: Span,
+    /// The key with its quotes.
+    pub key: Option<Span>,
+}
+
+/// Spans of every value of a document, keyed by JSON Pointer (RFC 6901) and in the
+/// order the values start in.
+///
+/// Returned by [`ParseOptions::parse_spanned`] next to the [`Value`], so diagnostics
+/// about a part of the value can point at the input:
+///
+/// ```
+/// let (_, spans) = json_rs_prac::from_str_spanned("{\n  \"port\": \"80\"\n}").unwrap();
+/// let span = spans.get("/port").unwrap();
+/// assert_eq!((span.value.start.line, span.value.start.column), (2, 11));
+/// assert_eq!(span.key.unwrap().start.column, 3);
+/// ```
+///
+/// With a repeated key the entry is for the member that ended up in the value, as
+/// [`duplicate_keys`](ParseOptions::duplicate_keys) decides.
+#[derive(Debug, Clone, Default)]
+pub struct Spans {
+    map: Map<String, NodeSpan>,
+}
+
+impl Spans {
+    /// Spans of the value at `pointer`, `""` being the whole document.
+    pub fn get(&self, pointer: &str) -> Option<&NodeSpan> {
+        self.map.get(pointer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Pointers and spans of all values in the order they start in.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &NodeSpan)> {
+        self.map
+            .iter()
+            .map(|(pointer, span)| (pointer.as_str(), span))
+    }
+}
+
+/// The span of a value while parsing.
+struct Entry {
+    pointer: String,
+    span: NodeSpan,
+    /// Index of the first entry after the value and everything in it.
+    end: usize,
+    /// Whether a later member with the same key took its place.
+    replaced: bool,
+}
+
+/// Spans in the order the values start in, so that a value and everything in it take
+/// up a range of entries.
+#[derive(Default)]
+struct Entries {
+    entries: Vec<Entry>,
+    /// Index of the entry of each pointer that wasn't replaced.
+    index: HashMap<String, usize>,
+}
+
+impl Entries {
+    fn push(&mut self, pointer: String, span: NodeSpan) -> usize {
+        let idx = self.entries.len();
+        self.index.insert(pointer.clone(), idx);
+        self.entries.push(Entry {
+            pointer,
+            span,
+            end: idx + 1,
+            replaced: false,
+        });
+        idx
+    }
+
+    /// Forgets the value at `pointer` and everything in it. Ranges that were replaced
+    /// before are skipped, so each entry is only gone through once however often keys
+    /// repeat.
+    fn replace(&mut self, pointer: &str) {
+        let start = match self.index.get(pointer) {
+            Some(&start) => start,
+            None => return,
+        };
+        let mut idx = start;
+        while idx < self.entries[start].end {
+            let entry = &mut self.entries[idx];
+            if entry.replaced {
+                idx = entry.end;
+                continue;
+            }
+            entry.replaced = true;
+            self.index.remove(&entry.pointer);
+            idx += 1;
+        }
+    }
+
+    fn into_spans(self) -> Spans {
+        let map = self
+            .entries
+            .into_iter()
+            .filter(|entry| !entry.replaced)
+            .map(|entry| (entry.pointer, entry.span))
+            .collect();
+        Spans { map }
+    }
+}
+
+/// An open array or object.
+struct Open {
+    pointer: String,
+    array: bool,
+    /// Elements so far, for arrays.
+    items: usize,
+    /// Key of the member whose value comes next, for objects.
+    key: Option<(String, Span)>,
+    /// Its entry, `None` if its values don't end up in the document, as for the later
+    /// ones of repeated keys with `DuplicateKeys::FirstWins`.
+    entry: Option<usize>,
+}
+
+/// Parses `s` with the pull parser, noting where each value and key is.
+pub(crate) fn parse(s: &str, opts: &ParseOptions) -> Result<(Value, Spans), Error> {
+    check_input_len(s, 0, opts).map_err(|e| ParseError::new(s, e))?;
+    let opts = ParseOptions {
+        max_input_len: None,
+        ..opts.clone()
+    };
+    let mut parser = Parser::with_options(s, opts.clone());
+    let mut builder = Builder::new(opts.duplicate_keys);
+    let mut entries = Entries::default();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut value = None;
+
+    loop {
+        let (pos, event) = match parser.step() {
+            Ok(Step::Event(pos, event)) => (pos, event),
+            // The parser only ends after a complete value
+            Ok(Step::End) | Ok(Step::NeedMore) => {
+                return Ok((value.unwrap(), entries.into_spans()))
+            }
+            Err(e) => {
+                // Input after the value may be wrong
+                drop_iteratively(value);
+                return Err(parser.error(e).into());
+            }
+        };
+
+        match &event {
+            Event::Key(k) => {
+                let span = Span {
+                    start: pos,
+                    end: parser.key_end(),
+                };
+                if let Some(open) = stack.last_mut() {
+                    open.key = Some((pointer::escape(k), span));
+                }
+            }
+            Event::EndArray | Event::EndObject => {
+                let open = stack.pop().unwrap();
+                if let Some(idx) = open.entry {
+                    let end = entries.entries.len();
+                    let entry = &mut entries.entries[idx];
+                    entry.span.value.end = parser.position();
+                    entry.end = end;
+                }
+            }
+            _ => {
+                let (pointer, key, kept) = match stack.last_mut() {
+                    None => (String::new(), None, true),
+                    Some(open) if open.array => {
+                        open.items += 1;
+                        let pointer = format!("{}/{}", open.pointer, open.items - 1);
+                        (pointer, None, open.entry.is_some())
+                    }
+                    Some(open) => {
+                        let (token, span) = open.key.take().unwrap();
+                        let pointer = format!("{}/{}", open.pointer, token);
+                        let repeated = entries.index.contains_key(&pointer);
+                        let kept = open.entry.is_some()
+                            && !(repeated && opts.duplicate_keys == DuplicateKeys::FirstWins);
+                        if kept && repeated {
+                            entries.replace(&pointer);
+                        }
+                        (pointer, Some(span), kept)
+                    }
+                };
+
+                let span = Span {
+                    start: pos,
+                    end: parser.position(),
+                };
+                let entry = if kept {
+                    Some(entries.push(pointer.clone(), NodeSpan { value: span, key }))
+                } else {
+                    None
+                };
+                if let Event::StartArray | Event::StartObject = event {
+                    stack.push(Open {
+                        pointer,
+                        array: event == Event::StartArray,
+                        items: 0,
+                        key: None,
+                        entry,
+                    });
+                }
+            }
+        }
+
+        match builder.push(pos, event) {
+            Ok(Some(v)) => value = Some(v),
+            Ok(None) => {}
+            Err(kind) => return Err(parser.key_error(pos, kind).into()),
+        }
+    }
+}
+
+#[test]
+fn spans_test() {
+    let input = "{\"a\": [1, {\"b/c\": null}],\n \"d~\": \"\u{e9}\"}";
+    let (value, spans) = crate::from_str_spanned(input).unwrap();
+    assert_eq!(value, crate::from_str(input).unwrap());
+
+    let text = |span: Span| &input[span.start.offset..span.end.offset];
+    let found: Vec<_> = spans
+        .iter()
+        .map(|(pointer, span)| (pointer, text(span.value), span.key.map(text)))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("", input, None),
+            ("/a", "[1, {\"b/c\": null}]", Some("\"a\"")),
+            ("/a/0", "1", None),
+            ("/a/1", "{\"b/c\": null}", None),
+            ("/a/1/b~1c", "null", Some("\"b/c\"")),
+            ("/d~0", "\"\u{e9}\"", Some("\"d~\"")),
+        ]
+    );
+
+    let span = spans.get("/d~0").unwrap();
+    assert_eq!(
+        (span.key.unwrap().start.line, span.key.unwrap().start.column),
+        (2, 2)
+    );
+    assert_eq!((span.value.end.line, span.value.end.column), (2, 11));
+
+    let err = crate::from_str_spanned("[1, {\"a\" 2}]").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected ':', found `2` at line 1, column 10 in object item"
+    );
+}
+
+#[test]
+fn duplicate_keys_test() {
+    let input = "{\"a\": {\"x\": 1}, \"a\": [2]}";
+    let last_wins = ParseOptions::default();
+    let first_wins = ParseOptions {
+        duplicate_keys: DuplicateKeys::FirstWins,
+        ..Default::default()
+    };
+
+    for (opts, pointers) in [
+        (last_wins, ["", "/a", "/a/0"]),
+        (first_wins, ["", "/a", "/a/x"]),
+    ] {
+        let (_, spans) = opts.parse_spanned(input).unwrap();
+        let found: Vec<_> = spans.iter().map(|(pointer, _)| pointer).collect();
+        assert_eq!(found, pointers);
+    }
+
+    let input = r#"{"a": {"x": {"y": 1}, "x": 2}, "b": 0, "a": [3], "a": {"x": [4]}}"#;
+    let (_, spans) = crate::from_str_spanned(input).unwrap();
+    let found: Vec<_> = spans
+        .iter()
+        .map(|(pointer, span)| {
+            (
+                pointer,
+                &input[span.value.start.offset..span.value.end.offset],
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("", input),
+            ("/b", "0"),
+            ("/a", "{\"x\": [4]}"),
+            ("/a/x", "[4]"),
+            ("/a/x/0", "4"),
+        ]
+    );
+
+    // Replacing a member doesn't go through the spans of the others
+    let input = format!("{{{}\"a\": 0}}", "\"a\": [[1]], ".repeat(20_000));
+    let (_, spans) = crate::from_str_spanned(&input).unwrap();
+    assert_eq!(spans.len(), 2);
+}
@@ -0,0 +1,308 @@
+//! Streams of JSON values one after the other, either just concatenated as in
+//! `{"a":1}{"b":2}` or as JSON text sequences (RFC 7464), where each value is preceded
+//! by the record separator `0x1E`.
+//!
+//! ```
+//! use json_rs_prac::stream::{self, Framing, StreamDeserializer};
+//! use json_rs_prac::{ParseOptions, Value};
+//!
+//! let mut values = stream::from_str("{\"a\": 1} [2]3");
+//! let (position, value) = values.next().unwrap().unwrap();
+//! assert_eq!((position.offset, value.to_string()), (0, "{\"a\":1}".to_string()));
+//! let (position, _) = values.next().unwrap().unwrap();
+//! assert_eq!(position.offset, 9);
+//! assert_eq!(values.count(), 1);
+//!
+//! let input = &b"\x1e{\"a\": 1}\n\x1e[2]\n"[..];
+//! let values = StreamDeserializer::with_options(input, ParseOptions::default(), Framing::Sequence);
+//! let offsets: Vec<_> = values.map(|res| res.unwrap().0.offset).collect();
+//! assert_eq!(offsets, [1, 11]);
+//! ```
+
+use std::io::{self, BufRead};
+
+use crate::error::RawError;
+use crate::ndjson::read_until;
+use crate::push::{PushParser, Status};
+use crate::{skip_spaces, Bom, Error, ParseError, ParseOptions, Position, Value};
+
+/// The record separator that starts each value of a JSON text sequence.
+const RS: u8 = 0x1e;
+
+/// How the values of a stream are told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// Values follow each other directly, with optional whitespace in between. Numbers
+    /// and the literals need whitespace or another value after them to tell where they
+    /// end, as in `1 2`.
+    #[default]
+    Concatenated,
+    /// JSON text sequences (RFC 7464): every value is preceded by `0x1E` and usually
+    /// followed by a newline. A value that fails to parse only loses its own record,
+    /// reading goes on with the next one. Empty records are left out.
+    Sequence,
+}
+
+/// Iterator over the values of a stream, together with the position each value starts
+/// at.
+///
+/// Positions, also in errors, are counted from the start of the stream. The input has
+/// to be UTF-8, [`detect_encoding`](ParseOptions::detect_encoding) is ignored. Reading
+/// ends after an I/O error, and with [`Framing::Concatenated`] after any error since
+/// there is no telling where the next value starts.
+pub struct StreamDeserializer<R> {
+    reader: R,
+    opts: ParseOptions,
+    framing: Framing,
+    /// Parses the whole stream with [`Framing::Concatenated`].
+    parser: PushParser,
+    /// The current record with [`Framing::Sequence`].
+    buf: Vec<u8>,
+    /// Where the next record starts with [`Framing::Sequence`].
+    position: Position,
+    /// Whether the part before the first record was read.
+    started: bool,
+    done: bool,
+}
+
+/// Parses the values of a string.
+pub fn from_str(s: &str) -> StreamDeserializer<&[u8]> {
+    StreamDeserializer::new(s.as_bytes())
+}
+
+/// Parses the values of a reader as they arrive.
+pub fn from_reader<R: BufRead>(reader: R) -> StreamDeserializer<R> {
+    StreamDeserializer::new(reader)
+}
+
+impl<R: BufRead> StreamDeserializer<R> {
+    /// Parses concatenated values with the default options.
+    pub fn new(reader: R) -> Self {
+        StreamDeserializer::with_options(reader, ParseOptions::default(), Framing::default())
+    }
+
+    /// Each value is parsed with `opts` and its own limits, except for
+    /// [`max_input_len`](ParseOptions::max_input_len): it limits the whole stream with
+    /// [`Framing::Concatenated`] and each record with [`Framing::Sequence`].
+    pub fn with_options(reader: R, opts: ParseOptions, framing: Framing) -> Self {
+        let opts = ParseOptions {
+            detect_encoding: false,
+            ..opts
+        };
+        StreamDeserializer {
+            reader,
+            parser: PushParser::stream(opts.clone()),
+            opts,
+            framing,
+            buf: Vec::new(),
+            position: Position::START,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// How many bytes of the input were used so far: up to the end of the last value
+    /// with [`Framing::Concatenated`], up to the end of its record with
+    /// [`Framing::Sequence`].
+    pub fn byte_offset(&self) -> usize {
+        match self.framing {
+            Framing::Concatenated => self.parser.position().offset,
+            Framing::Sequence => self.position.offset,
+        }
+    }
+
+    fn next_concatenated(&mut self) -> Option<Result<(Position, Value), Error>> {
+        loop {
+            match self.parser.next_value_at() {
+                Ok(Status::Ready(value)) => return Some(Ok(value)),
+                Ok(Status::NeedMore) => {}
+                Ok(Status::Done) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if chunk.is_empty() {
+                self.parser.finish();
+                continue;
+            }
+            let len = chunk.len();
+            let res = self.parser.feed(chunk);
+            self.reader.consume(len);
+            if let Err(e) = res {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    fn next_record(&mut self) -> Option<Result<(Position, Value), Error>> {
+        // The separator is kept too, so a record that was cut short is too long
+        let limit = self.opts.max_input_len.map_or(usize::MAX, |max| max + 1);
+        loop {
+            let start = self.position;
+            let len = match read_until(
+                &mut self.reader,
+                RS,
+                limit,
+                &mut self.buf,
+                &mut self.position,
+            ) {
+                Ok(0) => return None,
+                Ok(len) => len,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            let mut record = &self.buf[..];
+            if record.len() == len {
+                record = record.strip_suffix(&[RS]).unwrap_or(record);
+            }
+            let text = String::from_utf8_lossy(record);
+
+            if !self.started {
+                self.started = true;
+                let rest = skip_spaces(self.opts.skip_bom(&text), &self.opts);
+                if rest.is_empty() {
+                    continue;
+                }
+                let e = RawError::expected(rest, "record separator");
+                return Some(Err(ParseError::with_base(&text, e, start).into()));
+            }
+            if skip_spaces(&text, &self.opts).is_empty() {
+                continue;
+            }
+
+            // Only the stream as a whole may start with a byte order mark
+            let opts = ParseOptions {
+                bom: Bom::Reject,
+                ..self.opts.clone()
+            };
+            let value = match opts.parse_slice(record) {
+                Ok(value) => value,
+                Err(Error::Parse(e)) => return Some(Err(e.rebase(start).into())),
+                Err(e) => return Some(Err(e)),
+            };
+            return Some(self.check_end(&text, value, start));
+        }
+    }
+
+    /// Fails for a number or literal at the end of a record, which may have been cut
+    /// short (RFC 7464 section 2.4).
+    fn check_end(
+        &self,
+        text: &str,
+        value: Value,
+        start: Position,
+    ) -> Result<(Position, Value), Error> {
+        let value_start = start.advance(&text[..text.len() - skip_spaces(text, &self.opts).len()]);
+        let scalar = matches!(value, Value::Null | Value::Boolean(_) | Value::Number(_));
+        if scalar && !text.ends_with([' ', '\t', '\n', '\r']) {
+            let e = RawError::expected(&text[text.len()..], "whitespace");
+            return Err(ParseError::with_base(text, e, start).into());
+        }
+        Ok((value_start, value))
+    }
+}
+
+impl<R: BufRead> Iterator for StreamDeserializer<R> {
+    type Item = Result<(Position, Value), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = match self.framing {
+            Framing::Concatenated => self.next_concatenated(),
+            Framing::Sequence => self.next_record(),
+        };
+        self.done = match &res {
+            None | Some(Err(Error::Io(_))) => true,
+            Some(Err(_)) => self.framing == Framing::Concatenated,
+            Some(Ok(_)) => false,
+        };
+        res
+    }
+}
+
+#[test]
+fn concatenated_test() {
+    let input = "{\"a\":1}{\"b\":2}[]\n\"x\"1 2\ttrue null\n";
+    for size in 1..input.len() {
+        let chunks = io::BufReader::with_capacity(size, input.as_bytes());
+        let values: Vec<_> = from_reader(chunks)
+            .map(|res| res.map(|(pos, value)| (pos.offset, value.to_string())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected = [
+            (0, "{\"a\":1}"),
+            (7, "{\"b\":2}"),
+            (14, "[]"),
+            (17, "\"x\""),
+            (20, "1"),
+            (22, "2"),
+            (24, "true"),
+            (29, "null"),
+        ];
+        let expected: Vec<_> = expected.iter().map(|&(o, v)| (o, v.to_string())).collect();
+        assert_eq!(values, expected, "{}", size);
+    }
+
+    let mut values = from_str("  ");
+    assert!(values.next().is_none());
+    assert_eq!(values.byte_offset(), 2);
+
+    let results: Vec<_> = from_str("[1]\n{\"a\" 1}[2]")
+        .map(|res| res.map(|(pos, _)| pos.line).map_err(|e| e.to_string()))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            Ok(1),
+            Err("expected ':', found `1` at line 2, column 6 in object item".into())
+        ]
+    );
+}
+
+#[test]
+fn sequence_test() {
+    let input = "\x1e{\"a\": 1}\n\x1e\x1e  [1,\n\x1e123\x1e\u{feff}2\n\x1e\"\u{e9}\" \n\x1etrue\n";
+    let results: Vec<_> = StreamDeserializer::with_options(
+        input.as_bytes(),
+        ParseOptions::default(),
+        Framing::Sequence,
+    )
+    .map(|res| {
+        res.map(|(pos, value)| (pos.offset, pos.line, pos.column, value.to_string()))
+            .map_err(|e| e.to_string())
+    })
+    .collect();
+
+    assert_eq!(
+        results,
+        vec![
+            Ok((1, 1, 2, "{\"a\":1}".into())),
+            Err("expected value, found end of input at line 3, column 1 in array".into()),
+            Err("expected whitespace, found end of input at line 3, column 5".into()),
+            Err("expected value, found '\\u{feff}' at line 3, column 6".into()),
+            Ok((29, 4, 2, "\"\u{e9}\"".into())),
+            Ok((36, 5, 2, "true".into())),
+        ]
+    );
+
+    let results: Vec<_> = StreamDeserializer::with_options(
+        &b" {}\n\x1e1 "[..],
+        ParseOptions::default(),
+        Framing::Sequence,
+    )
+    .map(|res| res.map(|(_, value)| value).map_err(|e| e.to_string()))
+    .collect();
+    assert_eq!(
+        results,
+        vec![
+            Err("expected record separator, found '{' at line 1, column 2".into()),
+            Ok(crate::from_str("1").unwrap()),
+        ]
+    );
+}
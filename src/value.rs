@@ -0,0 +1,448 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::iter::FromIterator;
+use std::mem;
+use std::ops;
+
+use crate::{Map, Number, Value};
+
+/// What indexing a missing key or element gives.
+static NULL: Value = Value::Null;
+
+impl Value {
+    /// Value of `key` if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// Element `idx` if this is an array that long.
+    pub fn get_index(&self, idx: usize) -> Option<&Value> {
+        self.as_array()?.get(idx)
+    }
+
+    pub fn get_index_mut(&mut self, idx: usize) -> Option<&mut Value> {
+        self.as_array_mut()?.get_mut(idx)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        matches!(self, Value::Boolean(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Number(_))
+    }
+
+    /// Returns true for a number that [`as_i64`](Value::as_i64) would return.
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// Returns true for a number that [`as_u64`](Value::as_u64) would return.
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    /// Returns true for a number that [`as_f64`](Value::as_f64) would return.
+    pub fn is_f64(&self) -> bool {
+        self.as_f64().is_some()
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, Value::String(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Value::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, Value::Object(_))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// See [`Number::as_i64`].
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    /// See [`Number::as_u64`].
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64()
+    }
+
+    /// Only gives numbers that convert to `f64` exactly, see [`Number::as_f64`].
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number()?.as_f64()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Map<String, Value>> {
+        match self {
+            Value::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Map<String, Value>> {
+        match self {
+            Value::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    /// Moves the value out, leaving `null` in its place, to get at the parts of one
+    /// behind a reference without cloning them.
+    pub fn take(&mut self) -> Value {
+        mem::replace(self, Value::Null)
+    }
+
+    /// Drops the value one array or object at a time.
+    ///
+    /// Dropping a value the usual way recurses once per level of nesting. That is fine
+    /// for anything parsed with a [`max_depth`](crate::ParseOptions::max_depth), but a
+    /// value built with [`iterative`](crate::ParseOptions::iterative) parsing and no
+    /// limit can be nested deeply enough to overflow the stack.
+    pub fn drop_iteratively(self) {
+        crate::drop_iteratively(Some(self));
+    }
+
+    /// Name of the kind of value, for messages.
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+}
+
+/// Gives `null` unless this is an object with `key`.
+impl ops::Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Gives `null` unless this is an array with element `idx`.
+impl ops::Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, idx: usize) -> &Value {
+        self.get_index(idx).unwrap_or(&NULL)
+    }
+}
+
+/// Inserts `key` with a `null` value if it is missing, and turns a `null` into an empty
+/// object first, so a
+/// path like `value["a"]["b"]` can be assigned to starting from `null`.
+///
+/// # Panics
+///
+/// If the value is neither an object nor `null`.
+impl ops::IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        if self.is_null() {
+            *self = Value::Object(Map::new());
+        }
+        let kind = self.kind();
+        let obj = match self.as_object_mut() {
+            Some(obj) => obj,
+            None => panic!("cannot index {} with key {:?}", kind, key),
+        };
+
+        if !obj.contains_key(key) {
+            obj.insert(key.to_string(), Value::Null);
+        }
+        obj.get_mut(key).unwrap()
+    }
+}
+
+/// # Panics
+///
+/// If the value is not an array or `idx` is out of bounds.
+impl ops::IndexMut<usize> for Value {
+    fn index_mut(&mut self, idx: usize) -> &mut Value {
+        match self {
+            Value::Array(arr) => {
+                let len = arr.len();
+                match arr.get_mut(idx) {
+                    Some(item) => item,
+                    None => panic!("index {} out of bounds for array of length {}", idx, len),
+                }
+            }
+            _ => panic!("cannot index {} with {}", self.kind(), idx),
+        }
+    }
+}
+
+/// `()` becomes `null`, like serde serializes it.
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Null
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Value::Number(n)
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    Value::Number(n.into())
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize);
+
+/// Infinities and NaN become `null`, as when writing them.
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Number::from_f64(f).map_or(Value::Null, Value::Number)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Self {
+        Value::from(f as f64)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<Cow<'_, str>> for Value {
+    fn from(s: Cow<'_, str>) -> Self {
+        Value::String(s.into_owned())
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Self {
+        Value::String(c.to_string())
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(opt: Option<T>) -> Self {
+        opt.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(items: &[T]) -> Self {
+        Value::Array(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl From<Map<String, Value>> for Value {
+    fn from(obj: Map<String, Value>) -> Self {
+        Value::Object(obj)
+    }
+}
+
+/// Members come in the order of the `HashMap`, which is arbitrary.
+impl<K: Into<String>, V: Into<Value>, S> From<HashMap<K, V, S>> for Value {
+    fn from(map: HashMap<K, V, S>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+macro_rules! from_tuple {
+    ($(($($name:ident)+))*) => {
+        $(
+            /// Tuples become arrays, like serde serializes them.
+            impl<$($name: Into<Value>),+> From<($($name,)+)> for Value {
+                #[allow(non_snake_case)]
+                fn from(($($name,)+): ($($name,)+)) -> Self {
+                    Value::Array(vec![$($name.into()),+])
+                }
+            }
+        )*
+    };
+}
+
+from_tuple! {
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+}
+
+/// Collects an array. Items have to be converted to `Value` first, since an iterator
+/// of pairs collects an object.
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Value::Array(iter.into_iter().collect())
+    }
+}
+
+/// Collects an object, a repeated key keeps its first position and its last value.
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Object(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn accessors_test() {
+    let value = crate::from_str(r#"{"a": [1, -2, 2.5, "x", true, null], "b": {"c": {}}}"#).unwrap();
+
+    assert_eq!(value["a"][0].as_u64(), Some(1));
+    assert_eq!(value["a"][1].as_i64(), Some(-2));
+    assert_eq!(value["a"][1].as_u64(), None);
+    assert_eq!(value["a"][2].as_f64(), Some(2.5));
+    assert_eq!(value["a"][3].as_str(), Some("x"));
+    assert_eq!(value["a"][4].as_bool(), Some(true));
+    assert!(value["a"][5].is_null());
+    assert_eq!(value["a"].as_array().map(Vec::len), Some(6));
+    assert!(value["b"]["c"].as_object().unwrap().is_empty());
+
+    // Anything missing is `null`
+    assert!(value["a"][6].is_null());
+    assert!(value["b"]["x"]["y"][0].is_null());
+    assert!(value[0].is_null());
+    assert_eq!(value.get("x"), None);
+    assert_eq!(value["a"].get_index(3), Some(&Value::String("x".into())));
+    assert_eq!(value["a"].get("0"), None);
+
+    assert!(value.is_object() && value["a"].is_array() && value["a"][3].is_string());
+    assert!(value["a"][0].is_number() && value["a"][0].is_u64() && !value["a"][2].is_i64());
+}
+
+#[test]
+fn index_mut_test() {
+    let mut value = Value::Null;
+    value["a"]["b"] = Value::Boolean(true);
+    value["c"] = Value::Array(vec![Value::Null]);
+    value["c"][0] = Value::String("x".into());
+    *value.get_mut("a").unwrap().get_mut("b").unwrap() = Value::Boolean(false);
+    assert_eq!(
+        value,
+        crate::from_str(r#"{"a": {"b": false}, "c": ["x"]}"#).unwrap()
+    );
+
+    let c = value["c"].take();
+    assert_eq!(c.as_array().unwrap().len(), 1);
+    assert!(value["c"].is_null());
+    assert_eq!(value.get_index_mut(0), None);
+    assert!(value.as_object().unwrap().contains_key("c"));
+
+    let res = std::panic::catch_unwind(|| Value::Boolean(true)["a"] = Value::Null);
+    assert!(res.is_err());
+    let res = std::panic::catch_unwind(|| Value::Array(Vec::new())[0] = Value::Null);
+    assert!(res.is_err());
+}
+
+#[test]
+fn from_test() {
+    assert_eq!(Value::from(1u8), Value::Number(1.into()));
+    assert_eq!(Value::from(-1i64), Value::Number((-1).into()));
+    assert_eq!(Value::from(0.5).as_f64(), Some(0.5));
+    assert_eq!(Value::from(f64::NAN), Value::Null);
+    assert_eq!(Value::from("a"), Value::String("a".into()));
+    assert_eq!(Value::from(None::<bool>), Value::Null);
+    assert_eq!(Value::from(Some(true)), Value::Boolean(true));
+    assert_eq!(Value::from(vec![1, 2]), crate::from_str("[1, 2]").unwrap());
+    assert_eq!(
+        Value::from((1, "a", (), ())),
+        Value::Array(vec![1.into(), "a".into(), Value::Null, Value::Null])
+    );
+
+    let map: BTreeMap<_, _> = vec![("b", 2), ("a", 1)].into_iter().collect();
+    assert_eq!(Value::from(map).to_string(), r#"{"a":1,"b":2}"#);
+
+    let arr: Value = (1..4).map(Value::from).collect();
+    assert_eq!(arr, crate::from_str("[1, 2, 3]").unwrap());
+    let obj: Value = vec![("x", 1), ("y", 2), ("x", 3)].into_iter().collect();
+    assert_eq!(obj.to_string(), r#"{"x":3,"y":2}"#);
+}
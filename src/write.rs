@@ -0,0 +1,302 @@
+use std::fmt;
+use std::io;
+use std::slice;
+use std::vec;
+
+use crate::Value;
+
+/// Options controlling how a [`Value`] is written out as JSON.
+///
+/// JSON has no infinities or NaN, so the numbers only [JSON5](crate::ParseOptions::json5)
+/// parsing gives are written as `null`; writing doesn't fail because of them.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Put every array element and object member on its own line, indented by this
+    /// string once per level of nesting. `None` writes everything on one line.
+    pub indent: Option<String>,
+    /// Write object members sorted by key instead of in insertion order.
+    pub sort_keys: bool,
+}
+
+impl WriteOptions {
+    /// Pretty printing with two spaces of indentation.
+    pub fn pretty() -> Self {
+        WriteOptions {
+            indent: Some("  ".into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_string(&self, value: &Value) -> String {
+        let mut buf = String::new();
+        // Writing into a `String` never fails
+        write_value(&mut buf, value, self).unwrap();
+        buf
+    }
+
+    pub fn to_writer(&self, writer: impl io::Write, value: &Value) -> io::Result<()> {
+        let mut adapter = IoWriter {
+            inner: writer,
+            error: None,
+        };
+
+        write_value(&mut adapter, value, self).map_err(|_| {
+            adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))
+        })
+    }
+}
+
+/// Lets the `fmt::Write` based serializer write into an `io::Write`, keeping the actual
+/// I/O error around since `fmt::Error` can't carry it.
+pub(crate) struct IoWriter<W> {
+    pub(crate) inner: W,
+    pub(crate) error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+fn write_newline(w: &mut impl fmt::Write, opts: &WriteOptions, depth: usize) -> fmt::Result {
+    if let Some(indent) = &opts.indent {
+        w.write_char('\n')?;
+        for _ in 0..depth {
+            w.write_str(indent)?;
+        }
+    }
+    Ok(())
+}
+
+/// An array or object that is being written, with the items that are left.
+enum Open<'v> {
+    Array(slice::Iter<'v, Value>),
+    Object(vec::IntoIter<(&'v String, &'v Value)>),
+}
+
+/// Writes `value` keeping the arrays and objects it is in on a stack of its own, so
+/// that values as deep as the iterative parser builds can be written as well.
+fn write_value(w: &mut impl fmt::Write, value: &Value, opts: &WriteOptions) -> fmt::Result {
+    // Open containers, each with whether an item of it was written yet
+    let mut stack: Vec<(Open, bool)> = Vec::new();
+    let mut next = Some(value);
+
+    loop {
+        match next.take() {
+            Some(Value::Null) => w.write_str("null")?,
+            Some(Value::Boolean(b)) => w.write_str(if *b { "true" } else { "false" })?,
+            // The serde serializer writes them as `null` too
+            Some(Value::Number(n)) if !n.is_finite() => w.write_str("null")?,
+            Some(Value::Number(n)) => write!(w, "{}", n)?,
+            Some(Value::String(s)) => write_string(w, s)?,
+            Some(Value::Array(arr)) if arr.is_empty() => w.write_str("[]")?,
+            Some(Value::Array(arr)) => {
+                w.write_char('[')?;
+                stack.push((Open::Array(arr.iter()), false));
+            }
+            Some(Value::Object(obj)) if obj.is_empty() => w.write_str("{}")?,
+            Some(Value::Object(obj)) => {
+                let mut members: Vec<_> = obj.iter().collect();
+                if opts.sort_keys {
+                    members.sort_by_key(|(key, _)| *key);
+                }
+                w.write_char('{')?;
+                stack.push((Open::Object(members.into_iter()), false));
+            }
+            None => {}
+        }
+
+        let depth = stack.len();
+        let (open, started) = match stack.last_mut() {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        let (key, item) = match open {
+            Open::Array(items) => (None, items.next()),
+            Open::Object(members) => match members.next() {
+                Some((key, item)) => (Some(key), Some(item)),
+                None => (None, None),
+            },
+        };
+
+        match item {
+            Some(item) => {
+                if *started {
+                    w.write_char(',')?;
+                }
+                *started = true;
+                write_newline(w, opts, depth)?;
+                if let Some(key) = key {
+                    write_string(w, key)?;
+                    w.write_str(if opts.indent.is_some() { ": " } else { ":" })?;
+                }
+                next = Some(item);
+            }
+            None => {
+                let close = match open {
+                    Open::Array(_) => ']',
+                    Open::Object(_) => '}',
+                };
+                stack.pop();
+                write_newline(w, opts, depth - 1)?;
+                w.write_char(close)?;
+            }
+        }
+    }
+}
+
+/// Writes `s` as a JSON string literal, escaping only what has to be escaped.
+pub(crate) fn write_string(w: &mut impl fmt::Write, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+
+    let mut start = 0;
+    for (idx, c) in s.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\u{0008}' => "\\b",
+            '\u{000c}' => "\\f",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{0000}'..='\u{001f}' => "",
+            _ => continue,
+        };
+
+        w.write_str(&s[start..idx])?;
+        if escape.is_empty() {
+            write!(w, "\\u{:04x}", c as u32)?;
+        } else {
+            w.write_str(escape)?;
+        }
+        start = idx + c.len_utf8();
+    }
+
+    w.write_str(&s[start..])?;
+    w.write_char('"')
+}
+
+/// Writes compact JSON, or pretty-printed JSON with the alternate flag (`{:#}`).
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let opts = if f.alternate() {
+            WriteOptions::pretty()
+        } else {
+            WriteOptions::default()
+        };
+        write_value(f, self, &opts)
+    }
+}
+
+/// Serializes `value` as compact JSON.
+///
+/// Infinities and NaN are written as `null`, see [`WriteOptions`].
+pub fn to_string(value: &Value) -> String {
+    WriteOptions::default().to_string(value)
+}
+
+/// Serializes `value` as JSON indented with two spaces.
+pub fn to_string_pretty(value: &Value) -> String {
+    WriteOptions::pretty().to_string(value)
+}
+
+/// Writes `value` as compact JSON into `writer`.
+pub fn to_writer(writer: impl io::Write, value: &Value) -> io::Result<()> {
+    WriteOptions::default().to_writer(writer, value)
+}
+
+/// Writes `value` as JSON indented with two spaces into `writer`.
+pub fn to_writer_pretty(writer: impl io::Write, value: &Value) -> io::Result<()> {
+    WriteOptions::pretty().to_writer(writer, value)
+}
+
+#[test]
+fn string_escape_test() {
+    let value = Value::String("a\"b\\c\n\u{1}\u{7f}\u{1f600}".into());
+    assert_eq!(
+        to_string(&value),
+        "\"a\\\"b\\\\c\\n\\u0001\u{7f}\u{1f600}\""
+    );
+}
+
+#[test]
+fn pretty_test() {
+    let value = crate::from_str(r#"[1, {"a": []}, [null, 0.5]]"#).unwrap();
+    assert_eq!(to_string(&value), r#"[1,{"a":[]},[null,0.5]]"#);
+    assert_eq!(
+        to_string_pretty(&value),
+        "[\n  1,\n  {\n    \"a\": []\n  },\n  [\n    null,\n    0.5\n  ]\n]"
+    );
+
+    let tabs = WriteOptions {
+        indent: Some("\t".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        tabs.to_string(&Value::Array(vec![Value::Null])),
+        "[\n\tnull\n]"
+    );
+}
+
+#[test]
+fn non_finite_test() {
+    let value = crate::ParseOptions::json5()
+        .parse_str("[Infinity, -Infinity, NaN, 1]")
+        .unwrap();
+    assert_eq!(to_string(&value), "[null,null,null,1]");
+}
+
+#[test]
+fn round_trip_test() {
+    let text = include_str!("../example.json");
+    let value = crate::from_str(text).unwrap();
+
+    assert_eq!(crate::from_str(&value.to_string()).unwrap(), value);
+    assert_eq!(crate::from_str(&format!("{:#}", value)).unwrap(), value);
+
+    for number in &[
+        "0",
+        "-0",
+        "1.0",
+        "1e300",
+        "-1.5e-7",
+        "18446744073709551615",
+        "-9223372036854775808",
+    ] {
+        let value = crate::from_str(number).unwrap();
+        assert_eq!(
+            crate::from_str(&value.to_string()).unwrap(),
+            value,
+            "{}",
+            number
+        );
+    }
+
+    let mut buf = Vec::new();
+    to_writer(&mut buf, &value).unwrap();
+    assert_eq!(buf, value.to_string().into_bytes());
+}
+
+#[test]
+fn deep_test() {
+    let depth = 100_000;
+    let opts = crate::ParseOptions {
+        iterative: true,
+        max_depth: None,
+        ..Default::default()
+    };
+    let text = "[{\"a\":".repeat(depth) + "[]" + &"}]".repeat(depth);
+    let value = opts.parse_str(&text).unwrap();
+
+    assert_eq!(to_string(&value), text);
+    let mut buf = Vec::new();
+    to_writer(&mut buf, &value).unwrap();
+    assert_eq!(buf, text.into_bytes());
+    value.drop_iteratively();
+}
@@ -0,0 +1,157 @@
+//! Runs the strict parser against the parsing corpus of JSONTestSuite
+//! (https://github.com/nst/JSONTestSuite, `test_parsing/`).
+//!
+//! `y_` files must be accepted, `n_` files rejected, and `i_` files may go either way
+//! but must not panic.
+
+use std::fs;
+use std::panic;
+use std::path::Path;
+
+use json_rs_prac::pull::Parser;
+use json_rs_prac::push::{PushParser, Status};
+use json_rs_prac::ParseOptions;
+
+fn parse(bytes: &[u8]) -> bool {
+    ParseOptions::strict().parse_slice(bytes).is_ok()
+}
+
+fn iterative(bytes: &[u8]) -> bool {
+    let opts = ParseOptions {
+        iterative: true,
+        max_depth: None,
+        ..ParseOptions::strict()
+    };
+    opts.parse_slice(bytes).is_ok()
+}
+
+fn detect_encoding(bytes: &[u8]) -> bool {
+    let opts = ParseOptions {
+        detect_encoding: true,
+        ..ParseOptions::strict()
+    };
+    opts.parse_slice(bytes).is_ok()
+}
+
+fn pull(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Parser::with_options(s, ParseOptions::strict()).all(|e| e.is_ok()),
+        Err(_) => false,
+    }
+}
+
+fn push_chunks<'a>(chunks: impl Iterator<Item = &'a [u8]>) -> bool {
+    let mut parser = PushParser::with_options(ParseOptions::strict());
+    for chunk in chunks {
+        if parser.feed(chunk).is_err() {
+            return false;
+        }
+        loop {
+            match parser.next_event() {
+                Ok(Status::Ready(_)) => {}
+                Ok(Status::NeedMore) => break,
+                Ok(Status::Done) => return true,
+                Err(_) => return false,
+            }
+        }
+    }
+
+    parser.finish();
+    loop {
+        match parser.next_event() {
+            Ok(Status::Ready(_)) => {}
+            Ok(Status::NeedMore) => panic!("NeedMore after finish"),
+            Ok(Status::Done) => return true,
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Splits the input at every byte offset, the result must not depend on where.
+fn push(bytes: &[u8]) -> bool {
+    let accepted = push_chunks(bytes.chunks(1));
+    if bytes.len() <= 2000 {
+        for at in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(at);
+            assert_eq!(
+                push_chunks(vec![a, b].into_iter()),
+                accepted,
+                "split at {}",
+                at
+            );
+        }
+    }
+    accepted
+}
+
+fn run(parse: fn(&[u8]) -> bool) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/JSONTestSuite");
+    let mut failures = Vec::new();
+    let mut count = 0;
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if !name.ends_with(".json") {
+            continue;
+        }
+
+        let bytes = fs::read(&path).unwrap();
+        count += 1;
+
+        match panic::catch_unwind(|| parse(&bytes)) {
+            Ok(accepted) if name.starts_with("y_") && !accepted => {
+                failures.push(format!("{}: rejected", name))
+            }
+            Ok(accepted) if name.starts_with("n_") && accepted => {
+                failures.push(format!("{}: accepted", name))
+            }
+            Ok(_) => {}
+            Err(_) => failures.push(format!("{}: panicked", name)),
+        }
+    }
+
+    assert!(count >= 318);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn json_test_suite() {
+    run(parse);
+}
+
+/// The pull parser doesn't recurse, so it handles the deeply nested cases as well.
+#[test]
+fn json_test_suite_iterative() {
+    run(iterative);
+}
+
+#[test]
+fn json_test_suite_detect_encoding() {
+    run(detect_encoding);
+
+    // These are up to the implementation, but should work with detection
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/JSONTestSuite");
+    for name in &[
+        "i_string_UTF-16LE_with_BOM.json",
+        "i_string_utf16BE_no_BOM.json",
+        "i_string_utf16LE_no_BOM.json",
+        "i_structure_UTF-8_BOM_empty_object.json",
+    ] {
+        assert!(
+            detect_encoding(&fs::read(dir.join(name)).unwrap()),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn json_test_suite_pull() {
+    run(pull);
+}
+
+#[test]
+fn json_test_suite_push() {
+    run(push);
+}